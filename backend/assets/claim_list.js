@@ -0,0 +1,59 @@
+function update_empty_text() {
+  const matches = document.getElementById("matches");
+  document.getElementById("no-scores").hidden = matches.children.length > 0;
+}
+
+function score_item(score) {
+  const link = document.createElement("a");
+  link.href = score.id;
+  link.textContent = `${score.color}: ${score.score}`;
+  if (score.station !== null) {
+    link.textContent += ` (${score.station})`;
+  }
+
+  const item = document.createElement("li");
+  item.dataset.id = score.id;
+  item.append(link);
+
+  return item;
+}
+
+function add_match(match) {
+  const heading = document.createElement("h2");
+  heading.className = "h5";
+  heading.textContent = match.label;
+
+  const list = document.createElement("ul");
+  list.append(...match.scores.map(score_item));
+
+  const section = document.createElement("section");
+  section.className = "match";
+  section.dataset.match = match.id;
+  section.append(heading, list);
+
+  // newest first, like the server renders it
+  document.getElementById("matches").prepend(section);
+  update_empty_text();
+}
+
+function remove_score(id) {
+  const item = document.querySelector(`li[data-id="${id}"]`);
+  const section = item?.closest("section.match");
+  item?.remove();
+
+  // nothing left to claim in this match
+  if (section && section.querySelector("li") === null) {
+    section.remove();
+  }
+  update_empty_text();
+}
+
+document.addEventListener("DOMContentLoaded", () => {
+  // relative to /claim/list
+  const source = new EventSource("../events/claims");
+
+  source.addEventListener("added", (event) => add_match(JSON.parse(event.data)));
+  source.addEventListener("removed", (event) =>
+    remove_score(JSON.parse(event.data).id),
+  );
+});
@@ -0,0 +1,47 @@
+const placement_classes = ["first", "second", "third"];
+
+function render_leaderboard(scores, highlight) {
+  const table = document.createElement("table");
+
+  scores.forEach((score, i) => {
+    const row = table.insertRow();
+
+    if (score.placement <= placement_classes.length) {
+      row.classList.add(placement_classes[score.placement - 1]);
+    }
+
+    if (i === highlight) {
+      row.classList.add("new");
+    }
+
+    const placement = row.insertCell();
+    placement.className = "placement";
+    placement.textContent = score.placement;
+
+    const name = row.insertCell();
+    name.className = "name";
+    name.textContent = score.nickname;
+
+    const points = row.insertCell();
+    points.className = "score";
+    points.textContent = score.score;
+  });
+
+  document.getElementById("leaderboard").replaceChildren(table);
+
+  const new_row = table.querySelector(".new");
+  if (new_row) {
+    new_row.scrollIntoView({ behavior: "smooth", block: "center" });
+  }
+}
+
+document.addEventListener("DOMContentLoaded", () => {
+  // relative, so it works no matter where the leaderboard is mounted, and
+  // for the same window (e.g. ?window=today) as the page itself
+  const source = new EventSource("events/leaderboard" + location.search);
+
+  source.addEventListener("leaderboard", (event) => {
+    const update = JSON.parse(event.data);
+    render_leaderboard(update.scores, update.highlight);
+  });
+});
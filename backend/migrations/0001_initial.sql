@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS UnclaimedScores (
+    id BLOB(16) PRIMARY KEY,
+    score INTEGER NOT NULL,
+    color TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS Scores (
+    id INTEGER PRIMARY KEY,
+    nickname TEXT NOT NULL,
+    score INTEGER NOT NULL
+);
@@ -0,0 +1,13 @@
+CREATE TABLE StationKeys (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    created_at INTEGER NOT NULL,
+    revoked_at INTEGER
+);
+
+-- a revoked station may get a new key under the same name
+CREATE UNIQUE INDEX StationKeysActiveName ON StationKeys (name) WHERE revoked_at IS NULL;
+
+ALTER TABLE UnclaimedScores ADD COLUMN station_id INTEGER REFERENCES StationKeys (id);
+ALTER TABLE Scores ADD COLUMN station_id INTEGER REFERENCES StationKeys (id);
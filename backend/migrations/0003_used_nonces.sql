@@ -0,0 +1,4 @@
+CREATE TABLE UsedNonces (
+    nonce TEXT PRIMARY KEY,
+    used_at INTEGER NOT NULL
+);
@@ -0,0 +1,16 @@
+ALTER TABLE UnclaimedScores ADD COLUMN submitted_at INTEGER NOT NULL DEFAULT 0;
+
+-- scores that were already waiting get a fresh start
+UPDATE UnclaimedScores SET submitted_at = CAST(strftime('%s', 'now') AS INTEGER);
+
+CREATE INDEX UnclaimedScoresSubmittedAt ON UnclaimedScores (submitted_at);
+
+-- unclaimed scores that nobody wanted, kept for statistics
+CREATE TABLE ExpiredScores (
+    id BLOB(16) PRIMARY KEY,
+    score INTEGER NOT NULL,
+    color TEXT NOT NULL,
+    station_id INTEGER REFERENCES StationKeys (id),
+    submitted_at INTEGER NOT NULL,
+    expired_at INTEGER NOT NULL
+);
@@ -0,0 +1,5 @@
+-- remembers which unclaimed scores were claimed, to tell double claims apart from unknown ids
+CREATE TABLE ClaimedScores (
+    id BLOB(16) PRIMARY KEY,
+    claimed_at INTEGER NOT NULL
+);
@@ -0,0 +1,2 @@
+-- needed to break ties by submission time, scores claimed before this migration stay NULL
+ALTER TABLE Scores ADD COLUMN submitted_at INTEGER;
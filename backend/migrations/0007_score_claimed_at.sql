@@ -0,0 +1,5 @@
+-- needed for leaderboards of the last hour, today, ...
+-- scores claimed before this migration stay NULL and only show up on the all-time leaderboard
+ALTER TABLE Scores ADD COLUMN claimed_at INTEGER;
+
+CREATE INDEX ScoresClaimedAt ON Scores (claimed_at);
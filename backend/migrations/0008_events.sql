@@ -0,0 +1,27 @@
+-- separate leaderboards, e.g. for different game modes or fairs
+CREATE TABLE Events (
+    id INTEGER PRIMARY KEY,
+    slug TEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL,
+    -- NULL uses the ordering from the configuration
+    ordering TEXT,
+    created_at INTEGER NOT NULL
+);
+
+-- everything that exists already belongs to the default event, served at the root
+INSERT INTO Events (id, slug, name, created_at)
+VALUES (1, 'default', 'Ropeat', CAST(strftime('%s', 'now') AS INTEGER));
+
+-- sqlite can't add columns with a REFERENCES clause and a non-NULL default,
+-- so these point to Events (id) without the database knowing
+ALTER TABLE StationKeys ADD COLUMN event_id INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE UnclaimedScores ADD COLUMN event_id INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE ExpiredScores ADD COLUMN event_id INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE Scores ADD COLUMN event_id INTEGER NOT NULL DEFAULT 1;
+
+-- station names only have to be unique within an event
+DROP INDEX StationKeysActiveName;
+CREATE UNIQUE INDEX StationKeysActiveName ON StationKeys (event_id, name) WHERE revoked_at IS NULL;
+
+CREATE INDEX UnclaimedScoresEvent ON UnclaimedScores (event_id);
+CREATE INDEX ScoresEvent ON Scores (event_id);
@@ -0,0 +1,11 @@
+-- lets the game retry a submission without creating the score twice
+CREATE TABLE IdempotencyKeys (
+    station_id INTEGER NOT NULL REFERENCES StationKeys (id),
+    request_id TEXT NOT NULL,
+    -- the json body of the first response, every retry gets the same
+    response TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (station_id, request_id)
+);
+
+CREATE INDEX IdempotencyKeysCreatedAt ON IdempotencyKeys (created_at);
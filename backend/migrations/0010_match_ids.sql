@@ -0,0 +1,5 @@
+-- the scores of all players of one round share a match id, NULL for single submissions
+ALTER TABLE UnclaimedScores ADD COLUMN match_id BLOB;
+ALTER TABLE ExpiredScores ADD COLUMN match_id BLOB;
+
+CREATE INDEX UnclaimedScoresMatch ON UnclaimedScores (match_id);
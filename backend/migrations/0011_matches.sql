@@ -0,0 +1,27 @@
+-- one round of the game, all scores submitted together belong to the same match
+CREATE TABLE Matches (
+    id BLOB(16) PRIMARY KEY,
+    event_id INTEGER NOT NULL REFERENCES Events (id),
+    station_id INTEGER REFERENCES StationKeys (id),
+    -- game mode as reported by the station
+    mode TEXT,
+    -- NULL if the station didn't report it
+    started_at INTEGER,
+    ended_at INTEGER NOT NULL
+);
+
+CREATE INDEX MatchesEvent ON Matches (event_id);
+
+-- single submissions used to have no match, now every one of them is a match of its own
+UPDATE UnclaimedScores SET match_id = randomblob(16) WHERE match_id IS NULL;
+UPDATE ExpiredScores SET match_id = randomblob(16) WHERE match_id IS NULL;
+
+INSERT INTO Matches (id, event_id, station_id, ended_at)
+SELECT match_id, MIN(event_id), MIN(station_id), COALESCE(MAX(submitted_at), 0) FROM (
+    SELECT match_id, event_id, station_id, submitted_at FROM UnclaimedScores
+    UNION ALL
+    SELECT match_id, event_id, station_id, submitted_at FROM ExpiredScores
+) GROUP BY match_id;
+
+-- claimed scores remember their match as well, older ones simply don't have one
+ALTER TABLE Scores ADD COLUMN match_id BLOB;
@@ -0,0 +1,5 @@
+-- short code the game shows next to the score, typed in by the player to claim it
+ALTER TABLE UnclaimedScores ADD COLUMN code TEXT;
+
+-- codes only have to be unique among the scores that can still be claimed
+CREATE UNIQUE INDEX UnclaimedScoresCode ON UnclaimedScores (event_id, code);
@@ -0,0 +1,14 @@
+-- raffle entries of the `database` raffle sink, the winner gets drawn from here
+CREATE TABLE RaffleEntries (
+    id INTEGER PRIMARY KEY,
+    -- slug of the event
+    event TEXT NOT NULL,
+    score_id BLOB(16) NOT NULL,
+    score INTEGER NOT NULL,
+    firstname TEXT NOT NULL,
+    lastname TEXT NOT NULL,
+    email TEXT NOT NULL,
+    occupation TEXT NOT NULL,
+    newsletter INTEGER NOT NULL,
+    claimed_at INTEGER NOT NULL
+);
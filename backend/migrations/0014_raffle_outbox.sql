@@ -0,0 +1,17 @@
+-- raffle entries waiting to be handed to the raffle sink, written together
+-- with the claim so a slow or broken sink can't lose them
+CREATE TABLE RaffleOutbox (
+    id INTEGER PRIMARY KEY,
+    -- the entry as JSON
+    entry TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at INTEGER NOT NULL,
+    last_error TEXT,
+    created_at INTEGER NOT NULL,
+    delivered_at INTEGER,
+    -- set once the worker gave up on the entry
+    failed_at INTEGER
+);
+
+CREATE INDEX RaffleOutboxPending ON RaffleOutbox (next_attempt_at)
+WHERE delivered_at IS NULL AND failed_at IS NULL;
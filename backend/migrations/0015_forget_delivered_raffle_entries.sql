@@ -0,0 +1,3 @@
+-- delivered raffle entries are deleted from the outbox now, they only kept
+-- names and email addresses around for nothing
+DELETE FROM RaffleOutbox WHERE delivered_at IS NOT NULL;
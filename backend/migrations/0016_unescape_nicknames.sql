@@ -0,0 +1,13 @@
+-- nicknames used to be stored html escaped, now they are stored as entered
+-- and escaped where they get rendered. `&amp;` goes last, so an escaped
+-- `&lt;` that somebody typed in stays `&lt;`.
+UPDATE Scores SET nickname =
+    REPLACE(
+        REPLACE(
+            REPLACE(
+                REPLACE(
+                    REPLACE(nickname, '&lt;', '<'),
+                    '&gt;', '>'),
+                '&quot;', '"'),
+            '&#39;', ''''),
+        '&amp;', '&');
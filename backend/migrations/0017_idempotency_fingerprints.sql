@@ -0,0 +1,4 @@
+-- which endpoint and body a request id was first used with, reusing it for
+-- something else gets rejected instead of answered with the old response.
+-- NULL for keys from before, those get pruned soon anyway
+ALTER TABLE IdempotencyKeys ADD COLUMN fingerprint TEXT;
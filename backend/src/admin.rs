@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use serde_derive::Deserialize;
+use serde_json::json;
+
+use crate::{
+    database::Database,
+    error::ApiError,
+    events::{self, Event},
+    helper::slow_equals,
+    outbox::{self, Outbox},
+    stations, LeaderboardConfig, LeaderboardError, ScoreOrdering,
+};
+
+#[derive(Deserialize, Debug)]
+pub struct NewStation {
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct StationPath {
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct OutboxPath {
+    id: i64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NewEvent {
+    slug: String,
+    name: String,
+    ordering: Option<ScoreOrdering>,
+}
+
+/// Every admin route needs the admin token from the configuration.
+pub fn require_admin(
+    headers: &HeaderMap,
+    config: &LeaderboardConfig,
+) -> Result<(), LeaderboardError> {
+    let Some(authorization) = headers.get(AUTHORIZATION) else {
+        return Err(LeaderboardError::MissingAuth);
+    };
+
+    if !slow_equals(authorization.as_bytes(), config.admin_token.as_bytes()) {
+        return Err(LeaderboardError::WrongAuth);
+    }
+
+    Ok(())
+}
+
+pub async fn list_keys(
+    headers: HeaderMap,
+    State(state): State<Arc<LeaderboardConfig>>,
+    Extension(database): Extension<Arc<Database>>,
+    Extension(event): Extension<Event>,
+) -> Result<impl IntoResponse, ApiError> {
+    require_admin(&headers, &state)?;
+
+    Ok(Json(stations::list_keys(&database.pool, event.id).await?))
+}
+
+pub async fn create_key(
+    headers: HeaderMap,
+    State(state): State<Arc<LeaderboardConfig>>,
+    Extension(database): Extension<Arc<Database>>,
+    Extension(event): Extension<Event>,
+    Json(station): Json<NewStation>, // put every extractor above this!
+) -> Result<impl IntoResponse, ApiError> {
+    require_admin(&headers, &state)?;
+
+    let name = station.name.trim();
+    if name.is_empty() {
+        return Err(LeaderboardError::IncompleteData("name".to_string()).into());
+    }
+
+    let key = stations::create_key(&database.pool, event.id, name).await?;
+
+    Ok((StatusCode::CREATED, Json(json!({"name": name, "key": key}))))
+}
+
+pub async fn revoke_key(
+    headers: HeaderMap,
+    State(state): State<Arc<LeaderboardConfig>>,
+    Path(station): Path<StationPath>,
+    Extension(database): Extension<Arc<Database>>,
+    Extension(event): Extension<Event>,
+) -> Result<impl IntoResponse, ApiError> {
+    require_admin(&headers, &state)?;
+
+    stations::revoke_key(&database.pool, event.id, &station.name).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_events(
+    headers: HeaderMap,
+    State(state): State<Arc<LeaderboardConfig>>,
+    Extension(database): Extension<Arc<Database>>,
+) -> Result<impl IntoResponse, ApiError> {
+    require_admin(&headers, &state)?;
+
+    Ok(Json(events::list(&database.pool).await?))
+}
+
+pub async fn create_event(
+    headers: HeaderMap,
+    State(state): State<Arc<LeaderboardConfig>>,
+    Extension(database): Extension<Arc<Database>>,
+    Json(event): Json<NewEvent>, // put every extractor above this!
+) -> Result<impl IntoResponse, ApiError> {
+    require_admin(&headers, &state)?;
+
+    let name = event.name.trim();
+    if name.is_empty() {
+        return Err(LeaderboardError::IncompleteData("name".to_string()).into());
+    }
+
+    let event = events::create(&database.pool, &event.slug, name, event.ordering).await?;
+
+    Ok((StatusCode::CREATED, Json(event)))
+}
+
+/// Raffle entries the outbox worker gave up on, with the last error of the sink.
+pub async fn list_failed_raffle_entries(
+    headers: HeaderMap,
+    State(state): State<Arc<LeaderboardConfig>>,
+    Extension(database): Extension<Arc<Database>>,
+) -> Result<impl IntoResponse, ApiError> {
+    require_admin(&headers, &state)?;
+
+    Ok(Json(outbox::failed(&database.pool).await?))
+}
+
+pub async fn retry_raffle_entry(
+    headers: HeaderMap,
+    State(state): State<Arc<LeaderboardConfig>>,
+    Path(path): Path<OutboxPath>,
+    Extension(database): Extension<Arc<Database>>,
+    Extension(outbox): Extension<Arc<Outbox>>,
+) -> Result<impl IntoResponse, ApiError> {
+    require_admin(&headers, &state)?;
+
+    outbox::retry(&database.pool, path.id).await?;
+    outbox.wake();
+
+    Ok(StatusCode::ACCEPTED)
+}
@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    database::Database, error::ApiError, events::Event, ranked_scores, LeaderboardConfig,
+    PlacementScoreRow, ScoreOrdering, TimeRange, WindowQuery,
+};
+
+pub const DEFAULT_LIMIT: usize = 50;
+pub const MAX_LIMIT: usize = 100;
+
+#[derive(Deserialize, Debug)]
+pub struct Pagination {
+    limit: Option<usize>,
+    offset: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct LeaderboardPage {
+    scores: Vec<PlacementScoreRow>,
+    ordering: ScoreOrdering,
+    #[serde(flatten)]
+    range: TimeRange,
+    total: usize,
+    limit: usize,
+    offset: usize,
+}
+
+/// The leaderboard for the game's end screen and the stream overlay.
+///
+/// Placements are computed over the whole leaderboard, so they stay the same
+/// no matter which page is requested.
+pub async fn leaderboard(
+    Query(pagination): Query<Pagination>,
+    Query(window): Query<WindowQuery>,
+    State(state): State<Arc<LeaderboardConfig>>,
+    Extension(database): Extension<Arc<Database>>,
+    Extension(event): Extension<Event>,
+) -> Result<impl IntoResponse, ApiError> {
+    let limit = pagination.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    let offset = pagination.offset.unwrap_or(0);
+
+    let ordering = event.ordering(&state);
+    let range = window.range(chrono::Local::now())?;
+    let scores = ranked_scores(&database, event.id, ordering, state.ranking, range).await?;
+    let total = scores.len();
+
+    Ok(Json(LeaderboardPage {
+        scores: scores.into_iter().skip(offset).take(limit).collect(),
+        ordering,
+        range,
+        total,
+        limit,
+        offset,
+    }))
+}
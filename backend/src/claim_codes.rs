@@ -0,0 +1,148 @@
+use std::sync::Arc;
+
+use askama::Template;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+    Extension,
+};
+use serde_derive::Deserialize;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use crate::{
+    database::Database, expiry, templating::ClaimCodeTemplate, Event, LeaderboardConfig,
+    LeaderboardError,
+};
+
+/// No 0/O, 1/I/L or U, so a code read off the screen can be typed without asking.
+const ALPHABET: &[u8] = b"23456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+pub const CODE_LENGTH: usize = 6;
+
+/// How often a new code is drawn if the first one is already taken.
+pub const MAX_ATTEMPTS: usize = 5;
+
+#[derive(Deserialize, Debug)]
+pub struct CodeQuery {
+    code: Option<String>,
+}
+
+/// A random code, taken from the randomness of a v4 uuid.
+pub fn generate() -> String {
+    let mut random = Uuid::new_v4().as_u128();
+
+    (0..CODE_LENGTH)
+        .map(|_| {
+            let index = (random % ALPHABET.len() as u128) as usize;
+            random /= ALPHABET.len() as u128;
+
+            ALPHABET[index] as char
+        })
+        .collect()
+}
+
+/// Whatever the player typed in, in the form it is stored in.
+///
+/// Case, spaces and dashes don't matter. `None` if it can't be a code at all.
+pub fn normalize(input: &str) -> Option<String> {
+    let code: String = input
+        .chars()
+        .filter(|x| !x.is_whitespace() && *x != '-')
+        .map(|x| x.to_ascii_uppercase())
+        .collect();
+
+    (code.len() == CODE_LENGTH && code.bytes().all(|x| ALPHABET.contains(&x))).then_some(code)
+}
+
+/// The id of the score that can be claimed with `code`.
+pub async fn find(
+    pool: &SqlitePool,
+    event_id: i64,
+    code: &str,
+    claimable_since: i64,
+) -> Result<Option<Uuid>, LeaderboardError> {
+    sqlx::query_scalar(
+        "SELECT id FROM UnclaimedScores WHERE event_id = ? AND code = ? AND submitted_at > ?;",
+    )
+    .bind(event_id)
+    .bind(code)
+    .bind(claimable_since)
+    .fetch_optional(pool)
+    .await
+    .map_err(LeaderboardError::FetchError)
+}
+
+fn render(code: &str, error_message: Option<String>) -> Result<String, LeaderboardError> {
+    ClaimCodeTemplate {
+        code: code.to_string(),
+        code_length: CODE_LENGTH,
+        error_message,
+    }
+    .render()
+    .map_err(LeaderboardError::RenderError)
+}
+
+/// Lets the player type in the code the game showed them and sends them to
+/// the claim form of their score.
+pub async fn claim_code_page(
+    State(state): State<Arc<LeaderboardConfig>>,
+    Query(query): Query<CodeQuery>,
+    Extension(database): Extension<Arc<Database>>,
+    Extension(event): Extension<Event>,
+) -> Result<Response, LeaderboardError> {
+    let Some(input) = query.code else {
+        return Ok(Html(render("", None)?).into_response());
+    };
+
+    let id = match normalize(&input) {
+        Some(code) => {
+            find(
+                &database.pool,
+                event.id,
+                &code,
+                expiry::claimable_since(state.claim_ttl),
+            )
+            .await?
+        }
+        None => None,
+    };
+
+    let Some(id) = id else {
+        let page = render(
+            &input,
+            Some("Diesen Code gibt es nicht (mehr). Vielleicht hast du dich vertippt oder der Score ist abgelaufen?".to_string()),
+        )?;
+
+        return Ok((StatusCode::NOT_FOUND, Html(page)).into_response());
+    };
+
+    Ok(Redirect::to(&format!(
+        "{}{}/claim/{id}",
+        state.base_url(),
+        event.prefix()
+    ))
+    .into_response())
+}
+
+#[test]
+fn generated_codes_are_valid() {
+    for _ in 0..100 {
+        let code = generate();
+
+        assert_eq!(code.len(), CODE_LENGTH);
+        assert_eq!(normalize(&code), Some(code));
+    }
+}
+
+#[test]
+fn typed_codes_get_normalized() {
+    assert_eq!(normalize("abc-def").as_deref(), Some("ABCDEF"));
+    assert_eq!(normalize(" 234 567 ").as_deref(), Some("234567"));
+
+    assert_eq!(normalize("ABCDE"), None);
+    assert_eq!(normalize("ABCDEFG"), None);
+    assert_eq!(normalize("ABCDE0"), None, "0 is too close to O");
+    assert_eq!(normalize("ÄBCDEF"), None);
+}
@@ -0,0 +1,193 @@
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    path::Path,
+    time::Duration,
+};
+
+use serde::Deserializer;
+use serde_derive::Deserialize;
+
+use crate::{
+    DatabaseConfig, LeaderboardError, OutboxConfig, RaffleConfig, RankingStrategy, ScoreOrdering,
+    SigningConfig,
+};
+
+/// Everything the server needs to know at startup.
+///
+/// The struct can be read from a TOML file, every field missing there falls
+/// back to its default. The binary layers environment variables and CLI flags
+/// on top of that.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LeaderboardConfig {
+    /// token for the `/admin` routes, e.g. to hand out station keys
+    pub admin_token: String,
+    /// address the http server listens on
+    pub bind: SocketAddr,
+    /// public url of the server, used for redirects
+    pub base_url: String,
+    pub database: DatabaseConfig,
+    /// seconds a submitted score can be claimed before it expires
+    pub claim_ttl: u64,
+    /// seconds between two runs of the task that archives expired scores
+    pub expiry_interval: u64,
+    /// require signed score submissions, see [`SigningConfig`]
+    pub signing: Option<SigningConfig>,
+    /// whether high scores or low scores (e.g. times) win
+    pub ordering: ScoreOrdering,
+    /// how equal scores get placed
+    pub ranking: RankingStrategy,
+    /// show every unclaimed score at `/claim/list`, otherwise scores can
+    /// only be claimed with the code the game shows. Off by default, anybody
+    /// who sees the list can claim every score on it.
+    pub public_claim_list: bool,
+    /// where raffle entries go, see [`RaffleConfig`]
+    pub raffle: RaffleConfig,
+    /// retries of raffle entries the sink didn't take, see [`OutboxConfig`]
+    pub raffle_outbox: OutboxConfig,
+}
+
+impl Default for LeaderboardConfig {
+    fn default() -> Self {
+        Self {
+            admin_token: String::new(),
+            bind: SocketAddr::from((Ipv4Addr::LOCALHOST, 3000)),
+            base_url: "http://localhost:3000".to_string(),
+            database: DatabaseConfig::file("leaderboard.db"),
+            claim_ttl: 60 * 60,
+            expiry_interval: 60,
+            signing: None,
+            ordering: ScoreOrdering::HigherIsBetter,
+            ranking: RankingStrategy::Competition,
+            public_claim_list: false,
+            raffle: RaffleConfig::default(),
+            raffle_outbox: OutboxConfig::default(),
+        }
+    }
+}
+
+impl LeaderboardConfig {
+    pub fn from_toml(content: &str) -> Result<Self, LeaderboardError> {
+        toml::from_str(content).map_err(|x| LeaderboardError::ConfigError(x.to_string()))
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, LeaderboardError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|x| {
+            LeaderboardError::ConfigError(format!("couldn't read {}: {x}", path.display()))
+        })?;
+
+        Self::from_toml(&content)
+    }
+
+    /// Catches configurations that would start, but never work.
+    pub fn validate(&self) -> Result<(), LeaderboardError> {
+        if self.signing.as_ref().is_some_and(|x| x.secret.is_empty()) {
+            return Err(LeaderboardError::ConfigError(
+                "signing is enabled, but the secret is empty".to_string(),
+            ));
+        }
+
+        self.raffle.validate()?;
+        self.raffle_outbox.validate()?;
+
+        if self.admin_token.is_empty() {
+            return Err(LeaderboardError::ConfigError(
+                "no admin token configured, nobody could hand out station keys".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// `base_url` without a trailing slash, so paths can simply be appended.
+    pub fn base_url(&self) -> &str {
+        self.base_url.trim_end_matches('/')
+    }
+}
+
+pub(crate) fn duration_from_millis<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Duration, D::Error> {
+    Ok(Duration::from_millis(serde::Deserialize::deserialize(
+        deserializer,
+    )?))
+}
+
+#[test]
+fn empty_config_uses_defaults() {
+    let config = LeaderboardConfig::from_toml("").unwrap();
+
+    assert_eq!(config.bind, SocketAddr::from((Ipv4Addr::LOCALHOST, 3000)));
+    assert_eq!(config.base_url(), "http://localhost:3000");
+    assert!(!config.public_claim_list, "only codes can claim scores");
+    assert!(
+        config.validate().is_err(),
+        "an admin token has to be configured"
+    );
+}
+
+#[test]
+fn config_file_gets_parsed() {
+    let config = LeaderboardConfig::from_toml(
+        r#"
+        admin_token = "secret"
+        bind = "0.0.0.0:8080"
+        base_url = "https://leaderboard.example.com/"
+        claim_ttl = 900
+        ordering = "lower_is_better"
+        ranking = "earlier_submission"
+        public_claim_list = true
+
+        [database]
+        path = "/var/lib/ropeat/leaderboard.db"
+        wal = false
+        busy_timeout_ms = 250
+
+        [signing]
+        secret = "shared"
+
+        [raffle]
+        sink = "csv"
+        path = "/var/lib/ropeat/raffle.csv"
+
+        [raffle_outbox]
+        max_attempts = 5
+        retry_delay = 10
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.admin_token, "secret");
+    assert_eq!(config.bind, SocketAddr::from(([0, 0, 0, 0], 8080)));
+    assert_eq!(config.base_url(), "https://leaderboard.example.com");
+    assert_eq!(config.claim_ttl, 900);
+    assert_eq!(config.ordering, ScoreOrdering::LowerIsBetter);
+    assert_eq!(config.ranking, RankingStrategy::EarlierSubmission);
+    assert!(config.public_claim_list);
+    assert_eq!(
+        config.database.path.unwrap(),
+        Path::new("/var/lib/ropeat/leaderboard.db")
+    );
+    assert!(!config.database.wal);
+    assert_eq!(config.database.busy_timeout, Duration::from_millis(250));
+
+    let signing = config.signing.unwrap();
+    assert_eq!(signing.secret, "shared");
+    assert_eq!(signing.max_age, 300);
+
+    assert_eq!(
+        config.raffle,
+        RaffleConfig::Csv {
+            path: "/var/lib/ropeat/raffle.csv".into()
+        }
+    );
+    assert_eq!(config.raffle_outbox.max_attempts, 5);
+    assert_eq!(config.raffle_outbox.retry_delay, 10);
+    assert_eq!(config.raffle_outbox.max_retry_delay, 3600);
+}
+
+#[test]
+fn unknown_keys_get_rejected() {
+    assert!(LeaderboardConfig::from_toml("tokn = \"typo\"").is_err());
+}
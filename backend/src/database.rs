@@ -0,0 +1,98 @@
+use std::{path::PathBuf, str::FromStr, time::Duration};
+
+use serde_derive::Deserialize;
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+    ConnectOptions, SqlitePool,
+};
+
+use crate::{migrations, LeaderboardError};
+
+/// Where and how the leaderboard database is stored.
+///
+/// Without a `path` the database lives in memory and is gone after a restart,
+/// which is what the tests want. The booth laptop should always use a file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub path: Option<PathBuf>,
+    pub wal: bool,
+    #[serde(
+        rename = "busy_timeout_ms",
+        deserialize_with = "crate::config::duration_from_millis"
+    )]
+    pub busy_timeout: Duration,
+}
+
+impl DatabaseConfig {
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            wal: true,
+            busy_timeout: Duration::from_secs(5),
+        }
+    }
+
+    pub fn file(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+            ..Self::in_memory()
+        }
+    }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self::file("leaderboard.db")
+    }
+}
+
+pub struct Database {
+    pub pool: SqlitePool,
+}
+
+impl Database {
+    pub async fn new(config: &DatabaseConfig) -> Result<Self, LeaderboardError> {
+        let pool = match &config.path {
+            Some(path) => {
+                let journal_mode = if config.wal {
+                    SqliteJournalMode::Wal
+                } else {
+                    SqliteJournalMode::Delete
+                };
+
+                let db_options = SqliteConnectOptions::new()
+                    .filename(path)
+                    .create_if_missing(true)
+                    .journal_mode(journal_mode)
+                    .busy_timeout(config.busy_timeout)
+                    .disable_statement_logging();
+
+                SqlitePoolOptions::new()
+                    .connect_with(db_options)
+                    .await
+                    .map_err(LeaderboardError::DatabaseSetup)?
+            }
+            None => {
+                let db_options = SqliteConnectOptions::from_str(":memory:")
+                    .map_err(LeaderboardError::DatabaseSetup)?
+                    .disable_statement_logging();
+
+                // every connection to `:memory:` opens its own empty database,
+                // so keep exactly one connection alive for the whole runtime
+                SqlitePoolOptions::new()
+                    .min_connections(1)
+                    .max_connections(1)
+                    .idle_timeout(None)
+                    .max_lifetime(None)
+                    .connect_with(db_options)
+                    .await
+                    .map_err(LeaderboardError::DatabaseSetup)?
+            }
+        };
+
+        migrations::run(&pool).await?;
+
+        Ok(Self { pool })
+    }
+}
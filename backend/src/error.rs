@@ -1,29 +1,56 @@
+use askama::Template;
 use axum::{
     body::Body,
-    response::{IntoResponse, Response},
+    response::{Html, IntoResponse, Response},
 };
 use reqwest::StatusCode;
 use serde_json::json;
 
-use crate::submission::SubmissionError;
+use crate::{
+    submission::SubmissionError,
+    templating::{AlreadyClaimedTemplate, ErrorTemplate},
+};
 
 #[derive(Debug)]
 pub enum LeaderboardError {
     AxumServer(std::io::Error),
     TcpListener(std::io::Error),
+    ConfigError(String),
     DatabaseSetup(sqlx::Error),
+    MigrationFailed(&'static str, sqlx::Error),
+    SchemaTooNew(i64),
     TransactionBeginError(sqlx::Error),
+    TransactionCommitError(sqlx::Error),
     MissingAuth,
     WrongAuth,
+    MissingSignature,
+    BadSignature,
+    StaleTimestamp,
+    ReusedNonce,
     InvalidId,
+    UnknownScore,
+    AlreadyClaimed,
+    StationExists(String),
+    UnknownStation(String),
+    UnknownEvent(String),
+    EventExists(String),
+    InvalidSlug(String),
     TransmitError(SubmissionError),
     InsertFailure(sqlx::Error),
     FetchError(sqlx::Error),
     DeleteError(sqlx::Error),
     RenderError(askama::Error),
+    MalformedBody(serde_json::Error),
     InvalidScore,
     MalformedColor,
     IncompleteData(String),
+    InvalidWindow(String),
+    InvalidRequestId(String),
+    InvalidMatch(String),
+    ClaimListHidden,
+    QrCodeError(String),
+    UnknownOutboxEntry(i64),
+    RequestIdReused(String),
 }
 
 impl std::fmt::Display for LeaderboardError {
@@ -35,26 +62,78 @@ impl std::fmt::Display for LeaderboardError {
             LeaderboardError::TcpListener(x) => {
                 write!(fmt, "Couldn't launch TcpListener! Reason: {x}")
             }
+            LeaderboardError::ConfigError(x) => {
+                write!(fmt, "The configuration is not valid! Reason: {x}")
+            }
             LeaderboardError::DatabaseSetup(x) => {
                 write!(
                     fmt,
                     "Something went wrong while starting the database! Reason: {x}"
                 )
             }
+            LeaderboardError::MigrationFailed(name, x) => {
+                write!(
+                    fmt,
+                    "Couldn't apply database migration `{name}`! Reason: {x}"
+                )
+            }
+            LeaderboardError::SchemaTooNew(x) => {
+                write!(
+                    fmt,
+                    "The database has schema version {x}, but this build only understands up to version {}. Refusing to start!",
+                    crate::migrations::latest_version()
+                )
+            }
             LeaderboardError::TransactionBeginError(x) => {
                 write!(fmt, "Couldn't start transaction. Reason: {x}")
             }
+            LeaderboardError::TransactionCommitError(x) => {
+                write!(fmt, "Couldn't commit transaction. Reason: {x}")
+            }
             LeaderboardError::MissingAuth => {
                 write!(fmt, "You didn't provide any credentials!")
             }
             LeaderboardError::WrongAuth => {
                 write!(fmt, "You didn't provide valid credentials!")
             }
+            LeaderboardError::MissingSignature => {
+                write!(fmt, "You didn't sign the request!")
+            }
+            LeaderboardError::BadSignature => {
+                write!(fmt, "The signature of the request is not valid!")
+            }
+            LeaderboardError::StaleTimestamp => {
+                write!(fmt, "The timestamp of the request is too old or too new!")
+            }
+            LeaderboardError::ReusedNonce => {
+                write!(fmt, "The nonce of the request was already used!")
+            }
             LeaderboardError::InvalidId => {
                 write!(fmt, "You didn't provide a valid id!")
             }
+            LeaderboardError::UnknownScore => {
+                write!(fmt, "There is no claimable score with this id!")
+            }
+            LeaderboardError::AlreadyClaimed => {
+                write!(fmt, "This score was already claimed!")
+            }
+            LeaderboardError::StationExists(x) => {
+                write!(fmt, "The station `{x}` already has an active key!")
+            }
+            LeaderboardError::UnknownStation(x) => {
+                write!(fmt, "There is no station `{x}` with an active key!")
+            }
+            LeaderboardError::UnknownEvent(x) => {
+                write!(fmt, "There is no event `{x}`!")
+            }
+            LeaderboardError::EventExists(x) => {
+                write!(fmt, "The event `{x}` already exists!")
+            }
+            LeaderboardError::InvalidSlug(x) => {
+                write!(fmt, "`{x}` can't be used as a slug!")
+            }
             LeaderboardError::TransmitError(x) => {
-                write!(fmt, "Couldn't transmit data to the HPI server! Reason: {x}")
+                write!(fmt, "Couldn't submit the raffle entry! Reason: {x}")
             }
             LeaderboardError::IncompleteData(x) => {
                 write!(fmt, "You didn't provide all necessary data points! ({x})")
@@ -71,94 +150,270 @@ impl std::fmt::Display for LeaderboardError {
             LeaderboardError::RenderError(x) => {
                 write!(fmt, "Couldn't render template! Reason: {x}")
             }
+            LeaderboardError::MalformedBody(x) => {
+                write!(fmt, "The request body is not valid! Reason: {x}")
+            }
             LeaderboardError::InvalidScore => {
                 write!(fmt, "The score is not valid!")
             }
             LeaderboardError::MalformedColor => {
                 write!(fmt, "The color is not valid!")
             }
+            LeaderboardError::InvalidWindow(x) => {
+                write!(fmt, "The time window is not valid! Reason: {x}")
+            }
+            LeaderboardError::InvalidRequestId(x) => {
+                write!(fmt, "The request id is not valid! Reason: {x}")
+            }
+            LeaderboardError::InvalidMatch(x) => {
+                write!(fmt, "The match is not valid! Reason: {x}")
+            }
+            LeaderboardError::ClaimListHidden => {
+                write!(fmt, "The claim list is disabled!")
+            }
+            LeaderboardError::QrCodeError(x) => {
+                write!(fmt, "Couldn't render QR code! Reason: {x}")
+            }
+            LeaderboardError::UnknownOutboxEntry(x) => {
+                write!(fmt, "There is no failed raffle entry with the id {x}!")
+            }
+            LeaderboardError::RequestIdReused(x) => {
+                write!(
+                    fmt,
+                    "The request id `{x}` was already used for a different request!"
+                )
+            }
         }
     }
 }
 
 impl std::error::Error for LeaderboardError {}
 
-impl IntoResponse for LeaderboardError {
-    fn into_response(self) -> axum::response::Response {
+impl LeaderboardError {
+    pub fn status(&self) -> StatusCode {
         match self {
+            LeaderboardError::MissingAuth
+            | LeaderboardError::WrongAuth
+            | LeaderboardError::MissingSignature
+            | LeaderboardError::BadSignature
+            | LeaderboardError::StaleTimestamp
+            | LeaderboardError::ReusedNonce => StatusCode::UNAUTHORIZED,
+            LeaderboardError::InvalidId
+            | LeaderboardError::IncompleteData(_)
+            | LeaderboardError::MalformedBody(_)
+            | LeaderboardError::InvalidScore
+            | LeaderboardError::MalformedColor
+            | LeaderboardError::InvalidWindow(_)
+            | LeaderboardError::InvalidSlug(_)
+            | LeaderboardError::InvalidRequestId(_)
+            | LeaderboardError::InvalidMatch(_) => StatusCode::BAD_REQUEST,
+            LeaderboardError::UnknownScore
+            | LeaderboardError::UnknownStation(_)
+            | LeaderboardError::UnknownEvent(_)
+            | LeaderboardError::ClaimListHidden
+            | LeaderboardError::UnknownOutboxEntry(_) => StatusCode::NOT_FOUND,
+            LeaderboardError::AlreadyClaimed
+            | LeaderboardError::StationExists(_)
+            | LeaderboardError::EventExists(_) => StatusCode::CONFLICT,
+            LeaderboardError::RequestIdReused(_) => StatusCode::UNPROCESSABLE_ENTITY,
             LeaderboardError::AxumServer(_)
             | LeaderboardError::TcpListener(_)
-            | LeaderboardError::DatabaseSetup(_) => {
-                unreachable!("The server is not even up!")
-            }
-            LeaderboardError::MissingAuth => Response::builder()
-                .status(StatusCode::UNAUTHORIZED)
-                .body(Body::from("You didn't provide a authorization token!"))
-                .unwrap(),
-            LeaderboardError::WrongAuth => Response::builder()
-                .status(StatusCode::UNAUTHORIZED)
-                .body(Body::from(
-                    "You didn't provide a valid authorization token!",
-                ))
-                .unwrap(),
-            LeaderboardError::InvalidId => Response::builder()
-                .status(StatusCode::BAD_REQUEST)
-                .body(Body::from(
-                    "The given id is malformed! Where did you get it from?",
-                ))
-                .unwrap(),
-            LeaderboardError::IncompleteData(x) => Response::builder()
-                .status(StatusCode::BAD_REQUEST)
-                .body(Body::from("you didn't enter all necessary data pieces"))
-                .unwrap(),
-            LeaderboardError::TransactionBeginError(_) => {
-                todo!("implement `500 internal server error`")
+            | LeaderboardError::ConfigError(_)
+            | LeaderboardError::DatabaseSetup(_)
+            | LeaderboardError::MigrationFailed(_, _)
+            | LeaderboardError::SchemaTooNew(_)
+            | LeaderboardError::TransactionBeginError(_)
+            | LeaderboardError::TransactionCommitError(_)
+            | LeaderboardError::TransmitError(_)
+            | LeaderboardError::InsertFailure(_)
+            | LeaderboardError::FetchError(_)
+            | LeaderboardError::DeleteError(_)
+            | LeaderboardError::RenderError(_)
+            | LeaderboardError::QrCodeError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// What the client gets to see, `None` for internal errors whose details
+    /// only belong in the log (in release builds, that is).
+    fn public_message(&self) -> Option<String> {
+        let message = match self {
+            LeaderboardError::MissingAuth => "You didn't provide a authorization token!".to_string(),
+            LeaderboardError::WrongAuth => {
+                "You didn't provide a valid authorization token!".to_string()
+            }
+            LeaderboardError::MissingSignature => "Missing signature".to_string(),
+            LeaderboardError::BadSignature => "Bad signature".to_string(),
+            LeaderboardError::StaleTimestamp => "Stale timestamp".to_string(),
+            LeaderboardError::ReusedNonce => "Reused nonce".to_string(),
+            LeaderboardError::InvalidId => {
+                "The given id is malformed! Where did you get it from?".to_string()
+            }
+            LeaderboardError::IncompleteData(x) => {
+                format!("you didn't enter all necessary data pieces ({x})")
+            }
+            LeaderboardError::MalformedBody(x) => format!("Malformed body: {x}"),
+            LeaderboardError::InvalidScore => "Invalid score".to_string(),
+            LeaderboardError::MalformedColor => "Malformed color".to_string(),
+            LeaderboardError::InvalidWindow(x) => format!("Invalid time window: {x}"),
+            LeaderboardError::InvalidRequestId(x) => format!("Invalid request id: {x}"),
+            LeaderboardError::InvalidMatch(x) => format!("Invalid match: {x}"),
+            LeaderboardError::ClaimListHidden => "Claim list is disabled".to_string(),
+            LeaderboardError::UnknownScore => {
+                "Diesen Score gibt es nicht (mehr). Vielleicht ist er abgelaufen?".to_string()
+            }
+            LeaderboardError::UnknownStation(x) => format!("Unknown station {x}"),
+            LeaderboardError::AlreadyClaimed => "Dieser Score wurde bereits eingelöst.".to_string(),
+            LeaderboardError::StationExists(x) => {
+                format!("Station {x} already has an active key")
+            }
+            LeaderboardError::UnknownEvent(x) => format!("Unknown event {x}"),
+            LeaderboardError::EventExists(x) => format!("Event {x} already exists"),
+            LeaderboardError::UnknownOutboxEntry(x) => format!("Unknown failed raffle entry {x}"),
+            LeaderboardError::RequestIdReused(x) => {
+                format!("Request id {x} was already used for a different request")
+            }
+            LeaderboardError::InvalidSlug(x) => format!(
+                "Invalid slug {x}, use up to 32 lowercase letters, digits and dashes"
+            ),
+            _ if cfg!(debug_assertions) => self.to_string(),
+            LeaderboardError::TransmitError(_) => "Wir konnten dich leider nicht in das Gewinnspiel-Formular eintragen. Bitte frage einen der anwesenden Standbetreuenden um Hilfe!".to_string(),
+            _ => return None,
+        };
+
+        Some(message)
+    }
+
+    fn log(&self) {
+        if self.status().is_server_error() {
+            eprintln!("{}: {self}", self.status());
+        }
+    }
+}
+
+/// Browsers get an HTML page.
+impl IntoResponse for LeaderboardError {
+    fn into_response(self) -> axum::response::Response {
+        self.log();
+
+        let status = self.status();
+        let page = match self {
+            LeaderboardError::AlreadyClaimed => AlreadyClaimedTemplate.render(),
+            _ => ErrorTemplate {
+                status: status.as_u16(),
+                message: self.public_message().unwrap_or_else(|| {
+                    "Da ist leider etwas schiefgelaufen. Bitte frage einen der anwesenden Standbetreuenden um Hilfe!".to_string()
+                }),
+            }
+            .render(),
+        };
+
+        match page {
+            Ok(page) => (status, Html(page)).into_response(),
+            Err(x) => {
+                eprintln!("Couldn't render error page! Reason: {x}");
+
+                Response::builder()
+                    .status(status)
+                    .body(Body::from(status.to_string()))
+                    .unwrap()
             }
-            LeaderboardError::TransmitError(x) => {
-                if cfg!(debug_assertions) {
-                    Response::builder()
-                        .status(StatusCode::INTERNAL_SERVER_ERROR)
-                        .body(Body::from(format!("TransmitError: {x}")))
-                        .unwrap()
-                } else {
-                    Response::builder()
-                        .status(StatusCode::INTERNAL_SERVER_ERROR)
-                        .body(Body::from("Wir konnten dich leider nicht in das Gewinnspiel-Formular eintragen. Bitte frage einen der anwesenden Standbetreuenden um Hilfe!"))
-                        .unwrap()
-                }
-            }
-            LeaderboardError::InsertFailure(_) => todo!("implement `500 internal server error`"),
-            LeaderboardError::FetchError(x) => {
-                if cfg!(debug_assertions) {
-                    Response::builder()
-                        .status(StatusCode::INTERNAL_SERVER_ERROR)
-                        .body(Body::from(format!("FetchError: {x}")))
-                        .unwrap()
-                } else {
-                    Response::builder()
-                        .status(StatusCode::INTERNAL_SERVER_ERROR)
-                        .body(Body::empty())
-                        .unwrap()
-                }
-            }
-            LeaderboardError::DeleteError(_) => todo!("implement error"),
-            LeaderboardError::RenderError(_) => todo!("implement `500 internal server error`"),
-            LeaderboardError::InvalidScore => Response::builder()
-                .status(StatusCode::BAD_REQUEST)
-                .header("Content-Type", "application/json")
-                .body(Body::from(json!({"error": "Invalid score"}).to_string()))
-                .unwrap(),
-            LeaderboardError::MalformedColor => Response::builder()
-                .status(StatusCode::BAD_REQUEST)
-                .header("Content-Type", "application/json")
-                .body(Body::from(json!({"error": "Malformed color"}).to_string()))
-                .unwrap(),
         }
     }
 }
 
+/// Errors of routes that are used by programs (`/backend/*`, `/admin/*`),
+/// they get a JSON body instead of an HTML page.
+#[derive(Debug)]
+pub struct ApiError(pub LeaderboardError);
+
+impl From<LeaderboardError> for ApiError {
+    fn from(value: LeaderboardError) -> Self {
+        ApiError(value)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> axum::response::Response {
+        self.0.log();
+
+        let message = self
+            .0
+            .public_message()
+            .unwrap_or_else(|| "Internal server error".to_string());
+
+        Response::builder()
+            .status(self.0.status())
+            .header("Content-Type", "application/json")
+            .body(Body::from(json!({"error": message}).to_string()))
+            .unwrap()
+    }
+}
+
 impl From<SubmissionError> for LeaderboardError {
     fn from(value: SubmissionError) -> Self {
         LeaderboardError::TransmitError(value)
     }
 }
+
+#[cfg(test)]
+async fn body_text(response: Response) -> String {
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn internal_errors_render_html_page() {
+    let response = LeaderboardError::DeleteError(sqlx::Error::RowNotFound).into_response();
+
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+    assert!(body_text(response).await.contains("500"));
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn api_errors_render_json() {
+    let response = ApiError(LeaderboardError::RenderError(askama::Error::Fmt)).into_response();
+
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(response.headers()["content-type"], "application/json");
+
+    let body: serde_json::Value = serde_json::from_str(&body_text(response).await).unwrap();
+    assert!(body["error"].is_string());
+}
+
+#[test]
+fn every_server_side_error_is_internal() {
+    let errors = [
+        LeaderboardError::ConfigError(String::new()),
+        LeaderboardError::SchemaTooNew(0),
+        LeaderboardError::TransactionBeginError(sqlx::Error::PoolClosed),
+        LeaderboardError::TransactionCommitError(sqlx::Error::PoolClosed),
+        LeaderboardError::InsertFailure(sqlx::Error::PoolClosed),
+        LeaderboardError::FetchError(sqlx::Error::PoolClosed),
+        LeaderboardError::DeleteError(sqlx::Error::PoolClosed),
+        LeaderboardError::RenderError(askama::Error::Fmt),
+    ];
+
+    for error in errors {
+        assert_eq!(error.status(), StatusCode::INTERNAL_SERVER_ERROR, "{error}");
+    }
+}
+
+#[test]
+fn client_errors_keep_their_message() {
+    assert_eq!(
+        LeaderboardError::InvalidScore.public_message().unwrap(),
+        "Invalid score"
+    );
+    assert_eq!(
+        LeaderboardError::UnknownScore.status(),
+        StatusCode::NOT_FOUND
+    );
+}
@@ -0,0 +1,165 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::{Path, Request, State},
+    middleware::Next,
+    response::{Redirect, Response},
+    Extension,
+};
+use serde_derive::Serialize;
+use sqlx::{prelude::FromRow, SqlitePool};
+
+use crate::{
+    database::Database, helper::unix_now, LeaderboardConfig, LeaderboardError, ScoreOrdering,
+};
+
+/// Slug of the event that is served at the root of the server.
+pub const DEFAULT_EVENT: &str = "default";
+
+/// Top level paths that would otherwise be shadowed by an event with that slug.
+const RESERVED_SLUGS: &[&str] = &[
+    "admin",
+    "api",
+    "assets",
+    "backend",
+    "claim",
+    "events",
+    "favicon.ico",
+    "robots.txt",
+];
+
+pub const MAX_SLUG_LENGTH: usize = 32;
+
+/// A leaderboard with its own scores, claim list and station keys,
+/// e.g. one per game mode or fair.
+#[derive(FromRow, Serialize, Debug, Clone)]
+pub struct Event {
+    #[serde(skip)]
+    pub id: i64,
+    pub slug: String,
+    pub name: String,
+    /// `None` uses the ordering from the configuration
+    pub ordering: Option<ScoreOrdering>,
+    pub created_at: i64,
+}
+
+impl Event {
+    pub fn ordering(&self, config: &LeaderboardConfig) -> ScoreOrdering {
+        self.ordering.unwrap_or(config.ordering)
+    }
+
+    /// Path the event's routes are mounted at, empty for the default event.
+    pub fn prefix(&self) -> String {
+        if self.slug == DEFAULT_EVENT {
+            String::new()
+        } else {
+            format!("/{}", self.slug)
+        }
+    }
+}
+
+/// Slugs end up in urls, so they are kept boring.
+pub fn valid_slug(slug: &str) -> bool {
+    !slug.is_empty()
+        && slug.len() <= MAX_SLUG_LENGTH
+        && slug
+            .chars()
+            .all(|x| x.is_ascii_lowercase() || x.is_ascii_digit() || x == '-')
+        && !RESERVED_SLUGS.contains(&slug)
+}
+
+pub async fn find(pool: &SqlitePool, slug: &str) -> Result<Event, LeaderboardError> {
+    sqlx::query_as::<_, Event>(
+        "SELECT id, slug, name, ordering, created_at FROM Events WHERE slug = ?;",
+    )
+    .bind(slug)
+    .fetch_optional(pool)
+    .await
+    .map_err(LeaderboardError::FetchError)?
+    .ok_or_else(|| LeaderboardError::UnknownEvent(slug.to_string()))
+}
+
+pub async fn create(
+    pool: &SqlitePool,
+    slug: &str,
+    name: &str,
+    ordering: Option<ScoreOrdering>,
+) -> Result<Event, LeaderboardError> {
+    if !valid_slug(slug) {
+        return Err(LeaderboardError::InvalidSlug(slug.to_string()));
+    }
+
+    sqlx::query_as::<_, Event>(
+        "INSERT INTO Events (slug, name, ordering, created_at) VALUES (?, ?, ?, ?)
+        RETURNING id, slug, name, ordering, created_at;",
+    )
+    .bind(slug)
+    .bind(name)
+    .bind(ordering)
+    .bind(unix_now())
+    .fetch_one(pool)
+    .await
+    .map_err(|x| match x {
+        sqlx::Error::Database(ref e) if e.is_unique_violation() => {
+            LeaderboardError::EventExists(slug.to_string())
+        }
+        x => LeaderboardError::InsertFailure(x),
+    })
+}
+
+pub async fn list(pool: &SqlitePool) -> Result<Vec<Event>, LeaderboardError> {
+    sqlx::query_as::<_, Event>(
+        "SELECT id, slug, name, ordering, created_at FROM Events ORDER BY created_at, id;",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(LeaderboardError::FetchError)
+}
+
+/// Makes the event from the `{event}` path segment available to the handlers.
+pub async fn resolve_event(
+    Extension(database): Extension<Arc<Database>>,
+    Path(params): Path<HashMap<String, String>>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, LeaderboardError> {
+    let slug = params.get("event").map_or(DEFAULT_EVENT, String::as_str);
+    let event = find(&database.pool, slug).await?;
+
+    request.extensions_mut().insert(event);
+
+    Ok(next.run(request).await)
+}
+
+/// `/{event}` to `/{event}/`, so relative urls on the leaderboard page work.
+pub async fn add_trailing_slash(
+    State(state): State<Arc<LeaderboardConfig>>,
+    Path(slug): Path<String>,
+) -> Redirect {
+    Redirect::permanent(&format!("{}/{slug}/", state.base_url()))
+}
+
+/// Same as [`resolve_event`] for the routes at the root of the server.
+pub async fn default_event(
+    Extension(database): Extension<Arc<Database>>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, LeaderboardError> {
+    let event = find(&database.pool, DEFAULT_EVENT).await?;
+
+    request.extensions_mut().insert(event);
+
+    Ok(next.run(request).await)
+}
+
+#[test]
+fn slugs_get_checked() {
+    assert!(valid_slug("gamescom-2026"));
+    assert!(valid_slug("time-trial"));
+    assert!(!valid_slug(""));
+    assert!(!valid_slug("Gamescom"));
+    assert!(!valid_slug("game mode"));
+    assert!(!valid_slug("ümlaut"));
+    assert!(!valid_slug("claim"));
+    assert!(!valid_slug(&"x".repeat(MAX_SLUG_LENGTH + 1)));
+}
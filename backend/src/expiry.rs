@@ -0,0 +1,118 @@
+use std::{sync::Arc, time::Duration};
+
+use sqlx::SqlitePool;
+
+use uuid::Uuid;
+
+use crate::{
+    database::Database, helper::unix_now, idempotency, live::LiveUpdates, LeaderboardError,
+};
+
+/// Unclaimed scores have to be submitted after this point in time to still be claimable.
+pub fn claimable_since(ttl: u64) -> i64 {
+    unix_now() - ttl as i64
+}
+
+/// Moves every unclaimed score that is at least `ttl` seconds old to `ExpiredScores`.
+///
+/// Returns the ids of the expired scores together with the id of their event.
+pub async fn expire_stale(
+    pool: &SqlitePool,
+    ttl: u64,
+) -> Result<Vec<(Uuid, i64)>, LeaderboardError> {
+    let cutoff = claimable_since(ttl);
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .map_err(LeaderboardError::TransactionBeginError)?;
+
+    sqlx::query(
+        "INSERT INTO ExpiredScores
+        (id, event_id, score, color, station_id, match_id, submitted_at, expired_at)
+        SELECT id, event_id, score, color, station_id, match_id, submitted_at, ?
+        FROM UnclaimedScores
+        WHERE submitted_at <= ?;",
+    )
+    .bind(unix_now())
+    .bind(cutoff)
+    .execute(&mut *transaction)
+    .await
+    .map_err(LeaderboardError::InsertFailure)?;
+
+    let expired = sqlx::query_as(
+        "DELETE FROM UnclaimedScores WHERE submitted_at <= ? RETURNING id, event_id;",
+    )
+    .bind(cutoff)
+    .fetch_all(&mut *transaction)
+    .await
+    .map_err(LeaderboardError::DeleteError)?;
+
+    transaction
+        .commit()
+        .await
+        .map_err(LeaderboardError::TransactionCommitError)?;
+
+    Ok(expired)
+}
+
+/// Periodically expires stale unclaimed scores and forgets old request ids
+/// for as long as the server runs.
+pub fn spawn(database: Arc<Database>, live: Arc<LiveUpdates>, ttl: u64, interval: u64) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval.max(1)));
+
+        loop {
+            interval.tick().await;
+
+            match expire_stale(&database.pool, ttl).await {
+                Ok(expired) => {
+                    for (id, event_id) in expired {
+                        live.publish_removed(event_id, id);
+                    }
+                }
+                Err(x) => eprintln!("{x}"),
+            }
+
+            // a retry after that would only create a score that expires right away
+            if let Err(x) = idempotency::prune(&database.pool, claimable_since(ttl)).await {
+                eprintln!("{x}");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn only_stale_scores_expire() {
+    let database = Database::new(&crate::DatabaseConfig::in_memory())
+        .await
+        .unwrap();
+
+    for (score, age) in [(1, 7200), (2, 10)] {
+        sqlx::query(
+            "INSERT INTO UnclaimedScores (id, score, color, submitted_at) VALUES (?, ?, '#123456', ?);",
+        )
+        .bind(uuid::Uuid::new_v4())
+        .bind(score)
+        .bind(unix_now() - age)
+        .execute(&database.pool)
+        .await
+        .unwrap();
+    }
+
+    assert_eq!(expire_stale(&database.pool, 3600).await.unwrap().len(), 1);
+    assert!(expire_stale(&database.pool, 3600).await.unwrap().is_empty());
+
+    let remaining: Vec<i32> = sqlx::query_scalar("SELECT score FROM UnclaimedScores;")
+        .fetch_all(&database.pool)
+        .await
+        .unwrap();
+    assert_eq!(remaining, vec![2]);
+
+    let archived: Vec<i32> = sqlx::query_scalar("SELECT score FROM ExpiredScores;")
+        .fetch_all(&database.pool)
+        .await
+        .unwrap();
+    assert_eq!(archived, vec![1]);
+}
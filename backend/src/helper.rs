@@ -1,3 +1,5 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
 pub fn slow_equals(a: &[u8], b: &[u8]) -> bool {
     let mut result = a.len() == b.len();
 
@@ -26,27 +28,9 @@ fn equal_values_get_accepted() {
     assert!(slow_equals("abcd".as_bytes(), "abcd".as_bytes()))
 }
 
-pub fn sanitize_name(name: String) -> String {
-    // See <https://stackoverflow.com/questions/7381974/which-characters-need-to-be-escaped-in-html#7382028>
-    name.replace("&", "&amp;")
-        .replace("<", "&lt;")
-        .replace(">", "&gt;")
-        .replace("\"", "&quot;")
-        .replace("'", "&#39;")
-}
-
-#[test]
-fn simple_xss_gets_replaced() {
-    assert_eq!(
-        sanitize_name("<script>alert(1);</script>".to_string()),
-        "&lt;script&gt;alert(1);&lt;/script&gt;".to_string()
-    );
-}
-
-#[test]
-fn all_evil_chars_get_replaced() {
-    assert_eq!(
-        sanitize_name("&<>\"'".to_string()),
-        "&amp;&lt;&gt;&quot;&#39;".to_string()
-    )
+/// Seconds since the unix epoch, which is how every timestamp in the database is stored.
+pub fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_secs() as i64)
 }
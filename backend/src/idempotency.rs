@@ -0,0 +1,171 @@
+use axum::http::HeaderMap;
+use sha2::{Digest, Sha256};
+use sqlx::{SqliteConnection, SqliteExecutor, SqlitePool};
+
+use crate::{helper::unix_now, LeaderboardError};
+
+pub const IDEMPOTENCY_HEADER: &str = "idempotency-key";
+pub const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+/// The request id of a submission, from the `Idempotency-Key` header or the
+/// `request_id` in the body. If both are set, they have to match.
+pub fn request_id(
+    headers: &HeaderMap,
+    from_body: Option<&str>,
+) -> Result<Option<String>, LeaderboardError> {
+    let from_header = headers
+        .get(IDEMPOTENCY_HEADER)
+        .map(|x| {
+            x.to_str()
+                .map_err(|_| LeaderboardError::InvalidRequestId("not ascii".to_string()))
+        })
+        .transpose()?;
+
+    let request_id = match (from_header, from_body) {
+        (Some(header), Some(body)) if header != body => {
+            return Err(LeaderboardError::InvalidRequestId(
+                "header and body differ".to_string(),
+            ));
+        }
+        (header, body) => header.or(body),
+    };
+
+    match request_id {
+        Some("") => Err(LeaderboardError::InvalidRequestId("empty".to_string())),
+        Some(x) if x.len() > MAX_REQUEST_ID_LENGTH => Err(LeaderboardError::InvalidRequestId(
+            format!("longer than {MAX_REQUEST_ID_LENGTH} bytes"),
+        )),
+        x => Ok(x.map(str::to_string)),
+    }
+}
+
+/// Tells requests apart, a retry has the same `endpoint` and `body` as the original.
+pub fn fingerprint(endpoint: &str, body: &[u8]) -> String {
+    let mut hash = Sha256::new();
+    hash.update(endpoint.as_bytes());
+    hash.update(b"\n");
+    hash.update(body);
+
+    hex::encode(hash.finalize())
+}
+
+/// The response to an earlier request with the same id from the same station.
+///
+/// Fails if the id was used for a request with a different `fingerprint`.
+pub async fn find<'e>(
+    executor: impl SqliteExecutor<'e>,
+    station_id: i64,
+    request_id: &str,
+    fingerprint: &str,
+) -> Result<Option<String>, LeaderboardError> {
+    let earlier: Option<(String, Option<String>)> = sqlx::query_as(
+        "SELECT response, fingerprint FROM IdempotencyKeys WHERE station_id = ? AND request_id = ?;",
+    )
+    .bind(station_id)
+    .bind(request_id)
+    .fetch_optional(executor)
+    .await
+    .map_err(LeaderboardError::FetchError)?;
+
+    match earlier {
+        Some((_, Some(earlier))) if earlier != fingerprint => {
+            Err(LeaderboardError::RequestIdReused(request_id.to_string()))
+        }
+        x => Ok(x.map(|(response, _)| response)),
+    }
+}
+
+/// Stores the response for retries. Has to happen in the same transaction as
+/// the insert it belongs to.
+///
+/// Returns `false` if a concurrent request with the same id was faster, the
+/// transaction has to be rolled back then.
+pub async fn remember(
+    connection: &mut SqliteConnection,
+    station_id: i64,
+    request_id: &str,
+    fingerprint: &str,
+    response: &str,
+) -> Result<bool, LeaderboardError> {
+    let result = sqlx::query(
+        "INSERT INTO IdempotencyKeys (station_id, request_id, fingerprint, response, created_at)
+        VALUES (?, ?, ?, ?, ?);",
+    )
+    .bind(station_id)
+    .bind(request_id)
+    .bind(fingerprint)
+    .bind(response)
+    .bind(unix_now())
+    .execute(connection)
+    .await;
+
+    match result {
+        Ok(_) => Ok(true),
+        Err(sqlx::Error::Database(ref e)) if e.is_unique_violation() => Ok(false),
+        Err(x) => Err(LeaderboardError::InsertFailure(x)),
+    }
+}
+
+/// Forgets request ids from before `cutoff`, their scores can't be claimed anymore anyway.
+pub async fn prune(pool: &SqlitePool, cutoff: i64) -> Result<(), LeaderboardError> {
+    sqlx::query("DELETE FROM IdempotencyKeys WHERE created_at <= ?;")
+        .bind(cutoff)
+        .execute(pool)
+        .await
+        .map_err(LeaderboardError::DeleteError)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+fn headers(request_id: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(IDEMPOTENCY_HEADER, request_id.parse().unwrap());
+    headers
+}
+
+#[test]
+fn request_id_comes_from_header_or_body() {
+    let none = HeaderMap::new();
+
+    assert_eq!(request_id(&none, None).unwrap(), None);
+    assert_eq!(
+        request_id(&headers("abc"), None).unwrap().as_deref(),
+        Some("abc")
+    );
+    assert_eq!(
+        request_id(&none, Some("abc")).unwrap().as_deref(),
+        Some("abc")
+    );
+    assert_eq!(
+        request_id(&headers("abc"), Some("abc")).unwrap().as_deref(),
+        Some("abc")
+    );
+}
+
+#[test]
+fn bad_request_ids_get_rejected() {
+    let none = HeaderMap::new();
+
+    assert!(request_id(&headers("abc"), Some("abd")).is_err());
+    assert!(request_id(&none, Some("")).is_err());
+    assert!(request_id(&none, Some(&"x".repeat(MAX_REQUEST_ID_LENGTH + 1))).is_err());
+}
+
+#[test]
+fn fingerprints_cover_endpoint_and_body() {
+    let body = br##"{"score": 1337, "color": "#ff0000"}"##;
+
+    assert_eq!(
+        fingerprint("submit_score", body),
+        fingerprint("submit_score", body)
+    );
+    assert_ne!(
+        fingerprint("submit_score", body),
+        fingerprint("submit_match", body)
+    );
+    assert_ne!(
+        fingerprint("submit_score", body),
+        fingerprint("submit_score", br##"{"score": 1338, "color": "#ff0000"}"##)
+    );
+}
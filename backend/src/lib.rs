@@ -1,70 +1,119 @@
+mod admin;
+mod api;
+mod claim_codes;
+mod config;
+mod database;
 mod error;
+mod events;
+mod expiry;
 mod helper;
+mod idempotency;
+mod live;
+mod matches;
+mod migrations;
+mod outbox;
+mod qr;
+mod raffle;
+mod ranking;
+mod signing;
 mod r#static;
+mod stations;
 mod submission;
 mod templating;
-
-pub use error::LeaderboardError;
-
-use regex::bytes::Match;
-use submission::HPIFormData;
+mod validation;
+mod window;
+
+pub use config::LeaderboardConfig;
+pub use database::DatabaseConfig;
+pub use error::{ApiError, LeaderboardError};
+pub use outbox::OutboxConfig;
+pub use raffle::RaffleConfig;
+pub use ranking::{RankingStrategy, ScoreOrdering};
+pub use signing::SigningConfig;
+pub use submission::{HpiFormConfig, HpiFormFields};
+
+use database::Database;
+use events::Event;
+use live::LiveUpdates;
+use matches::ReceivedMatch;
+use outbox::Outbox;
+use raffle::RaffleEntry;
 
 use askama::Template;
 use axum::{
-    extract::{Path, State},
-    http::{
-        header::{self, AUTHORIZATION},
-        HeaderMap,
-    },
-    response::{Html, IntoResponse, Redirect},
-    routing::{get, post},
-    Extension, Form, Json, Router,
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware,
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{delete, get, post},
+    Extension, Form, Router,
 };
 use r#static::{font, icon, robots, style};
 
 use serde_derive::{Deserialize, Serialize};
 use serde_json::json;
-use sqlx::{
-    prelude::FromRow,
-    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
-    ConnectOptions, SqlitePool,
-};
+use sqlx::{prelude::FromRow, Sqlite, SqliteConnection, Transaction};
 use std::{str::FromStr, sync::Arc};
 use templating::{ClaimFormTemplate, ClaimListTemplate, LeaderboardTemplate};
 use uuid::Uuid;
+use validation::ClaimFormErrors;
+use window::{TimeRange, WindowQuery};
 
-use crate::{
-    helper::slow_equals,
-    r#static::{form_style, script},
-};
+use crate::r#static::{claim_list_script, form_style, leaderboard_script, script};
 
 #[derive(Deserialize, Debug, Clone)]
 struct RecievedScore {
     score: i32,
     color: String,
+    /// lets the game retry without creating the score twice, see [`idempotency`]
+    request_id: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ClaimPath {
+    id: String,
 }
 
-#[derive(FromRow, Debug, Clone)]
+#[derive(FromRow, Serialize, Debug, Clone)]
 struct UnclaimedScoreRow {
     id: Uuid,
     score: i32,
     color: String,
+    #[serde(skip)]
+    station_id: Option<i64>,
+    #[sqlx(default)]
+    station: Option<String>,
+    #[sqlx(default)]
+    #[serde(skip)]
+    submitted_at: Option<i64>,
+    #[sqlx(default)]
+    #[serde(skip)]
+    match_id: Option<Uuid>,
+    /// only the player gets to see it, see [`claim_codes`]
+    #[sqlx(default)]
+    #[serde(skip)]
+    code: Option<String>,
 }
 
 #[derive(FromRow, Serialize, Deserialize)]
 pub struct ScoreRow {
+    id: i64,
     nickname: String,
     score: i32,
+    submitted_at: Option<i64>,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct PlacementScoreRow {
+    #[serde(skip)]
+    id: i64,
     nickname: String,
     score: i32,
     placement: u32,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Default)]
 struct ClaimScore {
     wants_leaderboard: Option<bool>,
     wants_raffle: Option<bool>,
@@ -83,145 +132,169 @@ struct ClaimScore {
     occupation: String,
 }
 
-impl From<ClaimScore> for HPIFormData {
-    fn from(val: ClaimScore) -> Self {
-        let data_protection = "Ja, ich stimme zu.".to_string();
-
-        let occupation = match val.occupation.as_str() {
-            "school" => "Schüler:in".to_string(),
-            "university" => "Student:in".to_string(),
-            "parent" => "Elternteil".to_string(),
-            "other" => "sontiges".to_string(),
-            _ => "sonstiges".to_string(),
-        };
-
-        let email_consent = match val.newsletter {
-            true => "yes".to_string(),
-            false => "no".to_string(),
-        };
-
-        HPIFormData {
-            firstname: val.firstname,
-            lastname: val.lastname,
-            email: val.email,
-            occupation,
-            email_consent,
-            data_processing_consent: data_protection,
-        }
+impl ClaimScore {
+    fn leaderboard_checked(&self) -> bool {
+        self.wants_leaderboard == Some(true)
     }
-}
 
-#[derive(Clone)]
-struct LeaderboardConfig<'a> {
-    base_url: &'a str,
-    token: &'a str,
-}
-
-struct Database {
-    pool: SqlitePool,
+    fn raffle_checked(&self) -> bool {
+        self.wants_raffle == Some(true)
+    }
 }
 
-impl Database {
-    pub async fn new() -> Result<Self, LeaderboardError> {
-        let db_options = SqliteConnectOptions::from_str(":memory:")
-            .map_err(LeaderboardError::DatabaseSetup)?
-            .create_if_missing(true)
-            .disable_statement_logging()
-            .to_owned();
-
-        let pool = SqlitePoolOptions::new()
-            .connect_with(db_options)
-            .await
-            .map_err(LeaderboardError::DatabaseSetup)?;
-
-        sqlx::query(
-            "CREATE TABLE IF NOT EXISTS UnclaimedScores (
-                id BLOB(16) PRIMARY KEY,
-                score INTEGER NOT NULL,
-                color TEXT NOT NULL
-            );",
-        )
-        .execute(&pool)
-        .await
-        .map_err(LeaderboardError::DatabaseSetup)?;
-
-        sqlx::query(
-            "CREATE TABLE IF NOT EXISTS Scores (
-                id INTEGER PRIMARY KEY,
-                nickname TEXT NOT NULL,
-                score INTEGER NOT NULL
-            );",
-        )
-        .execute(&pool)
-        .await
-        .map_err(LeaderboardError::DatabaseSetup)?;
-
-        Ok(Self { pool })
+impl ClaimScore {
+    fn raffle_entry(
+        self,
+        event: &Event,
+        score: &UnclaimedScoreRow,
+        claimed_at: i64,
+    ) -> RaffleEntry {
+        RaffleEntry {
+            event: event.slug.clone(),
+            score_id: score.id,
+            score: score.score,
+            firstname: self.firstname.trim().to_string(),
+            lastname: self.lastname.trim().to_string(),
+            email: self.email.trim().to_string(),
+            occupation: self.occupation,
+            newsletter: self.newsletter,
+            claimed_at,
+        }
     }
 }
 
-pub async fn routes(auth_token: &'static str) -> Result<Router, LeaderboardError> {
-    let state = LeaderboardConfig {
-        base_url: "http://localhost:3000",
-        token: auth_token,
-    };
-
-    let database = Arc::new(Database::new().await?);
+pub async fn routes(config: LeaderboardConfig) -> Result<Router, LeaderboardError> {
+    config.validate()?;
+
+    let database = Arc::new(Database::new(&config.database).await?);
+    let live = Arc::new(LiveUpdates::new());
+    let outbox = Arc::new(Outbox::new());
+    outbox::spawn(
+        database.clone(),
+        config
+            .raffle
+            .sink(&database.pool, config.raffle_outbox.timeout())?,
+        outbox.clone(),
+        config.raffle_outbox.clone(),
+    );
+    expiry::spawn(
+        database.clone(),
+        live.clone(),
+        config.claim_ttl,
+        config.expiry_interval,
+    );
+    let state = Arc::new(config);
 
     Ok(Router::new()
-        // the leaderboard
-        .route("/", get(leaderboard))
-        // submit from game
-        .route("/backend/submit_score", post(submit_score))
-        // frontend
-        .route("/claim/list", get(unclaimed_scores_list))
-        .route("/claim/{id}", get(claim_score_form))
-        .route("/claim/{id}", post(claim_score_submit))
+        // the default event lives at the root, every other one below its slug
+        .route(
+            "/",
+            get(leaderboard).route_layer(middleware::from_fn(events::default_event)),
+        )
+        .merge(event_routes().route_layer(middleware::from_fn(events::default_event)))
+        // the pages use relative urls, those need the trailing slash
+        .route("/{event}", get(events::add_trailing_slash))
+        .route(
+            "/{event}/",
+            get(leaderboard).route_layer(middleware::from_fn(events::resolve_event)),
+        )
+        .nest(
+            "/{event}",
+            event_routes().route_layer(middleware::from_fn(events::resolve_event)),
+        )
+        // event management
+        .route("/admin/events", get(admin::list_events))
+        .route("/admin/events", post(admin::create_event))
+        // raffle entries the sink never took
+        .route(
+            "/admin/raffle/failed",
+            get(admin::list_failed_raffle_entries),
+        )
+        .route(
+            "/admin/raffle/failed/{id}/retry",
+            post(admin::retry_raffle_entry),
+        )
         // static stuff
         .route("/assets/style.css", get(style))
         .route("/assets/form.css", get(form_style))
         .route("/assets/font.ttf", get(font))
         .route("/assets/script.js", get(script))
+        .route("/assets/leaderboard.js", get(leaderboard_script))
+        .route("/assets/claim_list.js", get(claim_list_script))
         .route("/favicon.ico", get(icon))
         .route("/robots.txt", get(robots))
         // database + state
         .layer(Extension(database))
+        .layer(Extension(live))
+        .layer(Extension(outbox))
         .with_state(state))
 }
 
+/// Everything that exists once per event, except for the leaderboard page itself.
+/// The handlers get the [`Event`] as an extension.
+fn event_routes() -> Router<Arc<LeaderboardConfig>> {
+    Router::new()
+        // read api
+        .route("/api/leaderboard", get(api::leaderboard))
+        // live updates
+        .route("/events/leaderboard", get(live::leaderboard_events))
+        .route("/events/claims", get(live::claim_events))
+        // submit from game
+        .route("/backend/submit_score", post(submit_score))
+        .route("/backend/submit_match", post(matches::submit_match))
+        // station key management
+        .route("/admin/keys", get(admin::list_keys))
+        .route("/admin/keys", post(admin::create_key))
+        .route("/admin/keys/{name}", delete(admin::revoke_key))
+        // frontend
+        .route("/claim", get(claim_codes::claim_code_page))
+        .route("/claim/list", get(unclaimed_scores_list))
+        .route("/claim/{id}", get(claim_score_form))
+        .route("/claim/{id}", post(claim_score_submit))
+        .route("/claim/{id}/qr.svg", get(qr::claim_qr_svg))
+        .route("/claim/{id}/qr.png", get(qr::claim_qr_png))
+}
+
+/// All scores claimed within `range`, best first and with their placement.
+async fn ranked_scores(
+    database: &Database,
+    event_id: i64,
+    ordering: ScoreOrdering,
+    strategy: RankingStrategy,
+    range: TimeRange,
+) -> Result<Vec<PlacementScoreRow>, LeaderboardError> {
+    let scores = sqlx::query_as::<_, ScoreRow>(&format!(
+        "SELECT id, nickname, score, submitted_at FROM Scores WHERE event_id = ?1
+        AND (?2 IS NULL OR claimed_at >= ?2) AND (?3 IS NULL OR claimed_at < ?3)
+        ORDER BY score {}, id;",
+        ordering.sql()
+    ))
+    .bind(event_id)
+    .bind(range.from)
+    .bind(range.to)
+    .fetch_all(&database.pool)
+    .await
+    .map_err(LeaderboardError::FetchError)?;
+
+    Ok(ranking::rank(scores, ordering, strategy))
+}
+
 async fn leaderboard(
+    State(state): State<Arc<LeaderboardConfig>>,
+    Query(window): Query<WindowQuery>,
     Extension(database): Extension<Arc<Database>>,
+    Extension(event): Extension<Event>,
 ) -> Result<impl IntoResponse, LeaderboardError> {
-    let scores =
-        sqlx::query_as::<_, ScoreRow>("SELECT nickname, score FROM Scores ORDER BY score DESC;")
-            .fetch_all(&database.pool)
-            .await
-            .map_err(LeaderboardError::FetchError)?;
-
-    let mut placement_scores: Vec<PlacementScoreRow> = Vec::new();
-
-    let mut last_score = -1;
-    let mut last_placement = 1;
-    for (i, score) in scores.into_iter().enumerate() {
-        if last_score == score.score {
-            placement_scores.push(PlacementScoreRow {
-                nickname: score.nickname,
-                score: score.score,
-                placement: last_placement,
-            });
-        } else {
-            last_score = score.score;
-            last_placement = (i + 1).try_into().unwrap();
-            placement_scores.push(PlacementScoreRow {
-                nickname: score.nickname,
-                score: score.score,
-                placement: last_placement,
-            });
-        }
-    }
+    let ordering = event.ordering(&state);
+    let range = window.range(chrono::Local::now())?;
+    let placement_scores =
+        ranked_scores(&database, event.id, ordering, state.ranking, range).await?;
 
     let leaderboard = LeaderboardTemplate {
+        name: event.name,
         scores: placement_scores,
+        ordering,
+        title: window.title(range),
     }
     .render()
     .map_err(LeaderboardError::RenderError)?;
@@ -229,91 +302,250 @@ async fn leaderboard(
     Ok(Html(leaderboard))
 }
 
-async fn submit_score(
-    headers: HeaderMap,
-    State(state): State<LeaderboardConfig<'_>>,
-    Extension(database): Extension<Arc<Database>>,
-    Json(score): Json<RecievedScore>, // put every extractor above this!
-) -> Result<impl IntoResponse, LeaderboardError> {
-    let Some(authorization) = headers.get(AUTHORIZATION) else {
-        return Err(LeaderboardError::MissingAuth);
-    };
-
-    if !slow_equals(authorization.as_bytes(), state.token.as_bytes()) {
+/// Checks the station key and, if configured, the signature of a submission.
+///
+/// Returns the station the submission comes from.
+async fn authenticate_submission(
+    headers: &HeaderMap,
+    state: &LeaderboardConfig,
+    database: &Database,
+    event: &Event,
+    body: &[u8],
+) -> Result<stations::Station, LeaderboardError> {
+    let station = stations::authenticate(headers, &database.pool).await?;
+
+    // keys only work for the event they were handed out for
+    if station.event_id != event.id {
         return Err(LeaderboardError::WrongAuth);
     }
 
-    if score.score < 0 {
-        return Err(LeaderboardError::InvalidScore);
+    if let Some(signing) = &state.signing {
+        signing::verify(signing, headers, body, &database.pool).await?;
     }
 
-    if score.color.len() != 7 {
-        return Err(LeaderboardError::MalformedColor);
-    }
+    Ok(station)
+}
 
-    if !score.color.starts_with("#") {
-        return Err(LeaderboardError::MalformedColor);
-    }
+impl RecievedScore {
+    fn validate(&self) -> Result<(), LeaderboardError> {
+        if self.score < 0 {
+            return Err(LeaderboardError::InvalidScore);
+        }
 
-    if score
-        .color
-        .chars()
-        .filter(|x| x.is_ascii_hexdigit())
-        .count()
-        != 6
-    {
-        return Err(LeaderboardError::MalformedColor);
-    }
+        if self.color.len() != 7 {
+            return Err(LeaderboardError::MalformedColor);
+        }
+
+        if !self.color.starts_with("#") {
+            return Err(LeaderboardError::MalformedColor);
+        }
+
+        if self.color.chars().filter(|x| x.is_ascii_hexdigit()).count() != 6 {
+            return Err(LeaderboardError::MalformedColor);
+        }
 
-    //todo!("Validate score::color");
+        //todo!("Validate score::color");
 
+        Ok(())
+    }
+}
+
+/// Adds a score of the match `match_id` to the claim list.
+async fn insert_unclaimed(
+    connection: &mut SqliteConnection,
+    event_id: i64,
+    station: &stations::Station,
+    score: &RecievedScore,
+    match_id: Uuid,
+    submitted_at: i64,
+) -> Result<UnclaimedScoreRow, LeaderboardError> {
     let id = Uuid::new_v4();
 
-    // add score to unclaimed scores
-    sqlx::query("INSERT INTO UnclaimedScores (id, score, color) VALUES (?, ?, ?);")
+    let mut attempts = 1;
+    let code = loop {
+        let code = claim_codes::generate();
+
+        let result = sqlx::query(
+            "INSERT INTO UnclaimedScores
+            (id, event_id, score, color, station_id, match_id, code, submitted_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?);",
+        )
         .bind(id)
+        .bind(event_id)
         .bind(score.score)
-        .bind(score.color)
-        .execute(&database.pool)
+        .bind(&score.color)
+        .bind(station.id)
+        .bind(match_id)
+        .bind(&code)
+        .bind(submitted_at)
+        .execute(&mut *connection)
+        .await;
+
+        match result {
+            Ok(_) => break code,
+            // another claimable score has the same code, draw a new one
+            Err(sqlx::Error::Database(ref e))
+                if e.is_unique_violation() && attempts < claim_codes::MAX_ATTEMPTS =>
+            {
+                attempts += 1;
+            }
+            Err(x) => return Err(LeaderboardError::InsertFailure(x)),
+        }
+    };
+
+    Ok(UnclaimedScoreRow {
+        id,
+        score: score.score,
+        color: score.color.clone(),
+        station_id: Some(station.id),
+        station: Some(station.name.clone()),
+        submitted_at: Some(submitted_at),
+        match_id: Some(match_id),
+        code: Some(code),
+    })
+}
+
+/// Commits a submission together with its `response`, so a retry with the same
+/// request id gets the same one.
+///
+/// Returns the response of an earlier request if a retry overtook this one,
+/// nothing is committed then.
+async fn commit_submission(
+    database: &Database,
+    mut transaction: Transaction<'_, Sqlite>,
+    station_id: i64,
+    request_id: Option<&str>,
+    fingerprint: &str,
+    response: &str,
+) -> Result<Option<String>, LeaderboardError> {
+    if let Some(request_id) = request_id {
+        if !idempotency::remember(
+            &mut transaction,
+            station_id,
+            request_id,
+            fingerprint,
+            response,
+        )
+        .await?
+        {
+            drop(transaction);
+
+            return idempotency::find(&database.pool, station_id, request_id, fingerprint)
+                .await?
+                .ok_or(LeaderboardError::InvalidRequestId("vanished".to_string()))
+                .map(Some);
+        }
+    }
+
+    transaction
+        .commit()
         .await
-        .map_err(LeaderboardError::InsertFailure)?;
+        .map_err(LeaderboardError::TransactionCommitError)?;
+
+    Ok(None)
+}
+
+async fn submit_score(
+    headers: HeaderMap,
+    State(state): State<Arc<LeaderboardConfig>>,
+    Extension(database): Extension<Arc<Database>>,
+    Extension(live): Extension<Arc<LiveUpdates>>,
+    Extension(event): Extension<Event>,
+    body: Bytes, // put every extractor above this!
+) -> Result<impl IntoResponse, ApiError> {
+    let station = authenticate_submission(&headers, &state, &database, &event, &body).await?;
+
+    let score: RecievedScore =
+        serde_json::from_slice(&body).map_err(LeaderboardError::MalformedBody)?;
+    score.validate()?;
+
+    let request_id = idempotency::request_id(&headers, score.request_id.as_deref())?;
+    let fingerprint = idempotency::fingerprint("submit_score", &body);
+    if let Some(request_id) = &request_id {
+        if let Some(response) =
+            idempotency::find(&database.pool, station.id, request_id, &fingerprint).await?
+        {
+            return Ok(json_response(response));
+        }
+    }
+
+    let mut transaction = database
+        .pool
+        .begin()
+        .await
+        .map_err(LeaderboardError::TransactionBeginError)?;
+
+    // add score to unclaimed scores, as a match of its own
+    let inserted = ReceivedMatch::single(score)
+        .insert(&mut transaction, event.id, &station, helper::unix_now())
+        .await?;
+    let score = &inserted.scores[0];
+    let response = json!({"id": score.id.to_string(), "code": score.code}).to_string();
+
+    // a retry overtook us, it already inserted the score
+    if let Some(earlier) = commit_submission(
+        &database,
+        transaction,
+        station.id,
+        request_id.as_deref(),
+        &fingerprint,
+        &response,
+    )
+    .await?
+    {
+        return Ok(json_response(earlier));
+    }
+
+    live.publish_unclaimed(event.id, &inserted);
 
-    Ok(Json(json!({"id": id.to_string()})))
+    Ok(json_response(response))
+}
+
+/// Responses are stored pre-serialized for retries, see [`idempotency`].
+fn json_response(body: String) -> Response {
+    ([(header::CONTENT_TYPE, "application/json")], body).into_response()
 }
 
 async fn unclaimed_scores_list(
+    State(state): State<Arc<LeaderboardConfig>>,
     Extension(database): Extension<Arc<Database>>,
-) -> Result<impl IntoResponse, LeaderboardError> {
-    let unclaimed_scores =
-        sqlx::query_as::<_, UnclaimedScoreRow>("SELECT id, score, color FROM UnclaimedScores;")
-            .fetch_all(&database.pool)
-            .await
-            .map_err(LeaderboardError::FetchError)?;
+    Extension(event): Extension<Event>,
+) -> Result<Response, LeaderboardError> {
+    // scores can only be claimed with their code then
+    if !state.public_claim_list {
+        return Ok(Redirect::to(&claim_page(&state, &event)).into_response());
+    }
 
-    let unclaimed = ClaimListTemplate { unclaimed_scores }
+    // the expiry task only runs every now and then, so filter here as well
+    let matches = matches::unclaimed(
+        &database.pool,
+        event.id,
+        expiry::claimable_since(state.claim_ttl),
+    )
+    .await?;
+
+    let unclaimed = ClaimListTemplate { matches }
         .render()
         .map_err(LeaderboardError::RenderError)?;
 
-    Ok(Html(unclaimed))
+    Ok(Html(unclaimed).into_response())
 }
 
 async fn claim_score_form(
-    Path(id): Path<String>,
+    State(state): State<Arc<LeaderboardConfig>>,
+    Path(path): Path<ClaimPath>,
     Extension(database): Extension<Arc<Database>>,
+    Extension(event): Extension<Event>,
 ) -> Result<impl IntoResponse, LeaderboardError> {
-    let uuid = Uuid::from_str(&id).map_err(|_| LeaderboardError::InvalidId)?;
+    let uuid = Uuid::from_str(&path.id).map_err(|_| LeaderboardError::InvalidId)?;
 
-    let _unclaimed_scores = sqlx::query_as::<_, UnclaimedScoreRow>(
-        "SELECT id, score, color FROM UnclaimedScores WHERE id = ?;",
-    )
-    .bind(uuid)
-    .fetch_one(&database.pool)
-    .await
-    .map_err(LeaderboardError::FetchError)?;
+    ensure_claimable(&state, &database, &event, uuid).await?;
 
     let form = ClaimFormTemplate {
         id: uuid,
         error_message: None,
+        values: ClaimScore::default(),
+        errors: ClaimFormErrors::default(),
     }
     .render()
     .map_err(LeaderboardError::RenderError)?;
@@ -322,71 +554,156 @@ async fn claim_score_form(
 }
 
 async fn claim_score_submit(
-    State(state): State<LeaderboardConfig<'_>>,
-    Path(id): Path<String>,
+    State(state): State<Arc<LeaderboardConfig>>,
+    Path(path): Path<ClaimPath>,
     Extension(database): Extension<Arc<Database>>,
+    Extension(live): Extension<Arc<LiveUpdates>>,
+    Extension(outbox): Extension<Arc<Outbox>>,
+    Extension(event): Extension<Event>,
     Form(claim): Form<ClaimScore>, // put every extractor above this!
-) -> Result<impl IntoResponse, LeaderboardError> {
-    let id = Uuid::from_str(&id).map_err(|_| LeaderboardError::InvalidId)?;
+) -> Result<Response, LeaderboardError> {
+    let id = Uuid::from_str(&path.id).map_err(|_| LeaderboardError::InvalidId)?;
+
+    let errors = validation::validate_claim(&claim);
+    if !errors.is_empty() {
+        let form = ClaimFormTemplate {
+            id,
+            error_message: Some("Bitte überprüfe deine Eingaben.".to_string()),
+            values: claim,
+            errors,
+        }
+        .render()
+        .map_err(LeaderboardError::RenderError)?;
 
-    let mut submit_form = false;
-    let mut sanitized_nickname: Option<String> = None;
+        return Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(form)).into_response());
+    }
+
+    // stored as entered, the pages escape it
+    let nickname = claim
+        .leaderboard_checked()
+        .then(|| claim.nickname.trim().to_string());
+    let wants_raffle = claim.raffle_checked();
 
-    let score = sqlx::query_as::<_, UnclaimedScoreRow>(
-        "SELECT id, score, color FROM UnclaimedScores WHERE id = ?;",
+    // everything from here on happens at most once per score
+    let mut transaction = database
+        .pool
+        .begin()
+        .await
+        .map_err(LeaderboardError::TransactionBeginError)?;
+
+    let Some(score) = sqlx::query_as::<_, UnclaimedScoreRow>(
+        "DELETE FROM UnclaimedScores WHERE id = ? AND event_id = ? AND submitted_at > ?
+        RETURNING id, score, color, station_id, submitted_at, match_id;",
     )
     .bind(id)
-    .fetch_one(&database.pool)
+    .bind(event.id)
+    .bind(expiry::claimable_since(state.claim_ttl))
+    .fetch_optional(&mut *transaction)
     .await
-    .map_err(LeaderboardError::FetchError)?;
-
-    // leaderboard submission
-    if let Some(wants_leaderboard) = claim.wants_leaderboard {
-        if wants_leaderboard && claim.nickname.trim_end().is_empty() {
-            //LeaderboardError::IncompleteData
-            //return Ok(Redirect::to(&format!("{}/claim/{}", state.base_url, id)));
-            todo!("Redirect back to form, nickname not provided");
-        }
-
-        sanitized_nickname = Some(helper::sanitize_name(claim.nickname.trim_end().to_string()));
+    .map_err(LeaderboardError::DeleteError)?
+    else {
+        drop(transaction);
+        return Err(missing_score(&database, id).await);
     };
 
-    if let Some(wants_raffle) = claim.wants_raffle {
-        if wants_raffle
-            && (claim.email.trim_end().is_empty()
-                || claim.firstname.trim_end().is_empty()
-                || claim.lastname.trim_end().is_empty()
-                || claim.data_protection.is_none())
-        {
-            todo!("Redirect back to form, something not provided");
-        }
+    let claimed_at = helper::unix_now();
+    sqlx::query("INSERT INTO ClaimedScores (id, claimed_at) VALUES (?, ?);")
+        .bind(score.id)
+        .bind(claimed_at)
+        .execute(&mut *transaction)
+        .await
+        .map_err(LeaderboardError::InsertFailure)?;
 
-        submit_form = true;
+    let mut inserted = None;
+    if let Some(nickname) = nickname {
+        let id: i64 = sqlx::query_scalar(
+            "INSERT INTO Scores
+            (event_id, nickname, score, station_id, match_id, submitted_at, claimed_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?) RETURNING id;",
+        )
+        .bind(event.id)
+        .bind(nickname)
+        .bind(score.score)
+        .bind(score.station_id)
+        .bind(score.match_id)
+        .bind(score.submitted_at)
+        .bind(claimed_at)
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(LeaderboardError::InsertFailure)?;
+
+        inserted = Some(id);
     }
 
-    // ----------- RACE CONDITION ?! -----------
+    // the sink might be slow or down, the outbox worker takes care of it
+    if wants_raffle {
+        let entry = claim.raffle_entry(&event, &score, claimed_at);
 
-    // delete score
-    sqlx::query("DELETE FROM UnclaimedScores WHERE id = ?")
-        .bind(id)
-        .execute(&database.pool)
+        outbox::enqueue(&mut transaction, &entry).await?;
+    }
+
+    transaction
+        .commit()
         .await
-        .map_err(LeaderboardError::DeleteError)?;
-
-    if let Some(nickname) = sanitized_nickname {
-        sqlx::query("INSERT INTO Scores (nickname, score) VALUES (?, ?);")
-            .bind(nickname)
-            .bind(score.score)
-            .execute(&database.pool)
-            .await
-            .map_err(LeaderboardError::InsertFailure)?;
+        .map_err(LeaderboardError::TransactionCommitError)?;
+
+    live.publish_removed(event.id, score.id);
+    if let Some(inserted) = inserted {
+        live.publish_leaderboard(event.id, inserted);
+    }
+
+    if wants_raffle {
+        outbox.wake();
     }
 
-    if submit_form {
-        let form_data: HPIFormData = claim.into();
+    Ok(Redirect::to(&claim_page(&state, &event)).into_response())
+}
+
+/// Where players pick the next score to claim, the list or the code entry page.
+fn claim_page(state: &LeaderboardConfig, event: &Event) -> String {
+    let page = if state.public_claim_list {
+        "claim/list"
+    } else {
+        "claim"
+    };
+
+    format!("{}{}/{page}", state.base_url(), event.prefix())
+}
 
-        let _: () = submission::submit_form(form_data).await?;
+/// Fails with the reason if the score `id` of the event can't be claimed (anymore).
+async fn ensure_claimable(
+    state: &LeaderboardConfig,
+    database: &Database,
+    event: &Event,
+    id: Uuid,
+) -> Result<(), LeaderboardError> {
+    let unclaimed_score = sqlx::query_as::<_, UnclaimedScoreRow>(
+        "SELECT id, score, color, station_id FROM UnclaimedScores
+        WHERE id = ? AND event_id = ? AND submitted_at > ?;",
+    )
+    .bind(id)
+    .bind(event.id)
+    .bind(expiry::claimable_since(state.claim_ttl))
+    .fetch_optional(&database.pool)
+    .await
+    .map_err(LeaderboardError::FetchError)?;
+
+    match unclaimed_score {
+        Some(_) => Ok(()),
+        None => Err(missing_score(database, id).await),
     }
+}
 
-    Ok(Redirect::to(&format!("{}/claim/list", state.base_url)))
+async fn missing_score(database: &Database, id: Uuid) -> LeaderboardError {
+    let claimed =
+        sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM ClaimedScores WHERE id = ?);")
+            .bind(id)
+            .fetch_one(&database.pool)
+            .await;
+
+    match claimed {
+        Ok(true) => LeaderboardError::AlreadyClaimed,
+        Ok(false) => LeaderboardError::UnknownScore,
+        Err(x) => LeaderboardError::FetchError(x),
+    }
 }
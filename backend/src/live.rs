@@ -0,0 +1,197 @@
+use std::{convert::Infallible, sync::Arc};
+
+use axum::{
+    extract::{Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+    Extension,
+};
+use serde_derive::Serialize;
+use tokio::sync::broadcast;
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+use uuid::Uuid;
+
+use crate::{
+    database::Database, events, matches::ClaimListMatch, ranked_scores, LeaderboardConfig,
+    LeaderboardError, PlacementScoreRow, ScoreOrdering, WindowQuery,
+};
+
+/// Every update contains everything the page needs, so slow clients may
+/// simply miss a few.
+const CHANNEL_CAPACITY: usize = 16;
+
+#[derive(Serialize)]
+struct LeaderboardUpdate {
+    scores: Vec<PlacementScoreRow>,
+    ordering: ScoreOrdering,
+    /// index of the newly inserted row in `scores`
+    highlight: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct RemovedScore {
+    id: Uuid,
+}
+
+/// A named server-sent event with pre-serialized JSON data.
+#[derive(Clone)]
+struct LiveEvent {
+    event_id: i64,
+    name: &'static str,
+    data: String,
+}
+
+/// A score that just made it onto the leaderboard of an event.
+#[derive(Clone, Copy)]
+struct InsertedScore {
+    event_id: i64,
+    id: i64,
+}
+
+/// Pushes changes to every open page, e.g. the big screen or the claim tablet at the booth.
+///
+/// There is one channel for all events, every stream only picks the updates of its own.
+pub struct LiveUpdates {
+    /// every stream ranks these for its own window
+    leaderboard: broadcast::Sender<InsertedScore>,
+    claims: broadcast::Sender<LiveEvent>,
+}
+
+/// Serializes `data` and sends it as the event `name`, as long as anybody listens.
+fn publish(
+    sender: &broadcast::Sender<LiveEvent>,
+    event_id: i64,
+    name: &'static str,
+    data: &impl serde::Serialize,
+) {
+    if sender.receiver_count() == 0 {
+        return;
+    }
+
+    match serde_json::to_string(data) {
+        // nobody listening anymore is fine
+        Ok(data) => {
+            _ = sender.send(LiveEvent {
+                event_id,
+                name,
+                data,
+            })
+        }
+        Err(x) => eprintln!("Couldn't publish {name} update! Reason: {x}"),
+    }
+}
+
+impl LiveUpdates {
+    pub fn new() -> Self {
+        let (leaderboard, _) = broadcast::channel(CHANNEL_CAPACITY);
+        let (claims, _) = broadcast::channel(CHANNEL_CAPACITY);
+
+        Self {
+            leaderboard,
+            claims,
+        }
+    }
+
+    /// Tells all leaderboard pages of the event that the score with the id `inserted` was claimed.
+    pub fn publish_leaderboard(&self, event_id: i64, inserted: i64) {
+        // nobody listening is fine
+        _ = self.leaderboard.send(InsertedScore {
+            event_id,
+            id: inserted,
+        });
+    }
+
+    /// Tells the claim tablets of the event about a freshly submitted match.
+    pub fn publish_unclaimed(&self, event_id: i64, submitted: &ClaimListMatch) {
+        publish(&self.claims, event_id, "added", submitted);
+    }
+
+    /// Tells the claim tablets of the event that a score can't be claimed
+    /// anymore, because somebody claimed it or it expired.
+    pub fn publish_removed(&self, event_id: i64, id: Uuid) {
+        publish(&self.claims, event_id, "removed", &RemovedScore { id });
+    }
+}
+
+fn event_stream(
+    receiver: broadcast::Receiver<LiveEvent>,
+    event_id: i64,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    // lagging behind only happens if the client is really slow, skip what it missed
+    BroadcastStream::new(receiver)
+        .filter_map(|x| x.ok())
+        .filter(move |x| x.event_id == event_id)
+        .map(|x| Ok(Event::default().event(x.name).data(x.data)))
+}
+
+/// The ranking for `window` right now, highlighting the score with the id `inserted`.
+async fn leaderboard_update(
+    database: &Database,
+    state: &LeaderboardConfig,
+    event: &events::Event,
+    window: &WindowQuery,
+    inserted: i64,
+) -> Result<Event, LeaderboardError> {
+    let ordering = event.ordering(state);
+    let range = window.range(chrono::Local::now())?;
+    let scores = ranked_scores(database, event.id, ordering, state.ranking, range).await?;
+
+    let update = LeaderboardUpdate {
+        highlight: scores.iter().position(|x| x.id == inserted),
+        scores,
+        ordering,
+    };
+
+    Ok(Event::default()
+        .event("leaderboard")
+        .json_data(update)
+        .expect("updates always serialize"))
+}
+
+/// Takes the same `window` parameters as the leaderboard page.
+pub async fn leaderboard_events(
+    State(state): State<Arc<LeaderboardConfig>>,
+    Query(window): Query<WindowQuery>,
+    Extension(database): Extension<Arc<Database>>,
+    Extension(live): Extension<Arc<LiveUpdates>>,
+    Extension(event): Extension<events::Event>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, LeaderboardError> {
+    // fail right away instead of on the first update
+    window.range(chrono::Local::now())?;
+
+    let event_id = event.id;
+    let updates = BroadcastStream::new(live.leaderboard.subscribe())
+        .filter_map(|x| x.ok())
+        .filter(move |x| x.event_id == event_id)
+        .then(move |inserted| {
+            let (database, state, event, window) = (
+                database.clone(),
+                state.clone(),
+                event.clone(),
+                window.clone(),
+            );
+            async move { leaderboard_update(&database, &state, &event, &window, inserted.id).await }
+        })
+        // the claim is already done at this point, so errors only get logged
+        .filter_map(|x| match x {
+            Ok(event) => Some(Ok(event)),
+            Err(x) => {
+                eprintln!("Couldn't publish leaderboard update! Reason: {x}");
+                None
+            }
+        });
+
+    Ok(Sse::new(updates).keep_alive(KeepAlive::default()))
+}
+
+/// Only available together with the claim list, it contains the same ids.
+pub async fn claim_events(
+    State(state): State<Arc<LeaderboardConfig>>,
+    Extension(live): Extension<Arc<LiveUpdates>>,
+    Extension(event): Extension<events::Event>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, LeaderboardError> {
+    if !state.public_claim_list {
+        return Err(LeaderboardError::ClaimListHidden);
+    }
+
+    Ok(Sse::new(event_stream(live.claims.subscribe(), event.id)).keep_alive(KeepAlive::default()))
+}
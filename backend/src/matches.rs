@@ -0,0 +1,377 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use axum::{body::Bytes, extract::State, http::HeaderMap, response::Response, Extension};
+use chrono::{Local, TimeZone};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{prelude::FromRow, SqliteConnection, SqlitePool};
+use uuid::Uuid;
+
+use crate::{
+    authenticate_submission, commit_submission, database::Database, helper, idempotency,
+    insert_unclaimed, json_response, live::LiveUpdates, stations::Station, ApiError, Event,
+    LeaderboardConfig, LeaderboardError, RecievedScore, UnclaimedScoreRow,
+};
+
+/// More players than this don't fit around one Ropeat table.
+pub const MAX_PLAYERS: usize = 16;
+pub const MAX_MODE_LENGTH: usize = 32;
+
+/// All players of one round, every one of them identified by their color.
+#[derive(Deserialize, Debug)]
+pub struct ReceivedMatch {
+    scores: Vec<RecievedScore>,
+    /// game mode, shown on the claim list
+    mode: Option<String>,
+    /// seconds since the unix epoch
+    started_at: Option<i64>,
+    /// seconds since the unix epoch, defaults to the time of the submission
+    ended_at: Option<i64>,
+    /// same as for single scores, see [`idempotency`]
+    request_id: Option<String>,
+}
+
+/// One round of the game as stored, with the name of the station it was played at.
+#[derive(FromRow, Serialize, Debug, Clone)]
+pub struct Match {
+    pub id: Uuid,
+    #[sqlx(default)]
+    pub station: Option<String>,
+    pub mode: Option<String>,
+    pub started_at: Option<i64>,
+    pub ended_at: i64,
+}
+
+/// A match together with its scores that can still be claimed, in the order
+/// they were submitted.
+#[derive(Serialize, Debug)]
+pub struct ClaimListMatch {
+    #[serde(flatten)]
+    pub info: Match,
+    /// heading on the claim list
+    pub label: String,
+    pub scores: Vec<UnclaimedScoreRow>,
+}
+
+impl ReceivedMatch {
+    /// A single submitted score is a match with only one player.
+    pub fn single(score: RecievedScore) -> Self {
+        Self {
+            request_id: score.request_id.clone(),
+            scores: vec![score],
+            mode: None,
+            started_at: None,
+            ended_at: None,
+        }
+    }
+
+    fn validate(&self) -> Result<(), LeaderboardError> {
+        if self.scores.is_empty() {
+            return Err(LeaderboardError::IncompleteData("scores".to_string()));
+        }
+
+        if self.scores.len() > MAX_PLAYERS {
+            return Err(LeaderboardError::InvalidMatch(format!(
+                "more than {MAX_PLAYERS} players"
+            )));
+        }
+
+        if let Some(mode) = &self.mode {
+            let length = mode.trim().chars().count();
+
+            if length == 0 || length > MAX_MODE_LENGTH {
+                return Err(LeaderboardError::InvalidMatch(format!(
+                    "the mode has to have 1 to {MAX_MODE_LENGTH} characters"
+                )));
+            }
+        }
+
+        let mut colors = HashSet::new();
+        for score in &self.scores {
+            score.validate()?;
+
+            // the color is all a player has to find their score on the claim list
+            if !colors.insert(score.color.to_ascii_lowercase()) {
+                return Err(LeaderboardError::InvalidMatch(format!(
+                    "two players with the color {}",
+                    score.color
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stores the match and all of its scores as unclaimed.
+    pub async fn insert(
+        &self,
+        connection: &mut SqliteConnection,
+        event_id: i64,
+        station: &Station,
+        submitted_at: i64,
+    ) -> Result<ClaimListMatch, LeaderboardError> {
+        let ended_at = self.ended_at.unwrap_or(submitted_at);
+        if self.started_at.is_some_and(|x| x > ended_at) {
+            return Err(LeaderboardError::InvalidMatch(
+                "it ended before it started".to_string(),
+            ));
+        }
+
+        let info = Match {
+            id: Uuid::new_v4(),
+            station: Some(station.name.clone()),
+            mode: self.mode.as_deref().map(|x| x.trim().to_string()),
+            started_at: self.started_at,
+            ended_at,
+        };
+
+        sqlx::query(
+            "INSERT INTO Matches (id, event_id, station_id, mode, started_at, ended_at)
+            VALUES (?, ?, ?, ?, ?, ?);",
+        )
+        .bind(info.id)
+        .bind(event_id)
+        .bind(station.id)
+        .bind(&info.mode)
+        .bind(info.started_at)
+        .bind(info.ended_at)
+        .execute(&mut *connection)
+        .await
+        .map_err(LeaderboardError::InsertFailure)?;
+
+        let mut scores = Vec::with_capacity(self.scores.len());
+        for score in &self.scores {
+            scores.push(
+                insert_unclaimed(
+                    &mut *connection,
+                    event_id,
+                    station,
+                    score,
+                    info.id,
+                    submitted_at,
+                )
+                .await?,
+            );
+        }
+
+        Ok(ClaimListMatch::new(info, scores))
+    }
+}
+
+/// Only the time of day, the claim list only shows recent matches anyway.
+fn time_of_day(timestamp: i64) -> String {
+    Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .map(|x| x.format("%H:%M").to_string())
+        .unwrap_or_default()
+}
+
+impl Match {
+    pub fn label(&self) -> String {
+        let mut label = match self.started_at {
+            Some(started_at) => format!(
+                "Runde von {} bis {}",
+                time_of_day(started_at),
+                time_of_day(self.ended_at)
+            ),
+            None => format!("Runde um {}", time_of_day(self.ended_at)),
+        };
+
+        if let Some(mode) = &self.mode {
+            label.push_str(&format!(" · {mode}"));
+        }
+
+        label
+    }
+}
+
+impl ClaimListMatch {
+    fn new(info: Match, scores: Vec<UnclaimedScoreRow>) -> Self {
+        Self {
+            label: info.label(),
+            info,
+            scores,
+        }
+    }
+}
+
+/// Every match of the event that still has claimable scores, newest first.
+pub async fn unclaimed(
+    pool: &SqlitePool,
+    event_id: i64,
+    claimable_since: i64,
+) -> Result<Vec<ClaimListMatch>, LeaderboardError> {
+    let scores = sqlx::query_as::<_, UnclaimedScoreRow>(
+        "SELECT UnclaimedScores.id, score, color, station_id, StationKeys.name AS station, match_id
+        FROM UnclaimedScores LEFT JOIN StationKeys ON StationKeys.id = station_id
+        WHERE UnclaimedScores.event_id = ? AND submitted_at > ?
+        ORDER BY UnclaimedScores.rowid;",
+    )
+    .bind(event_id)
+    .bind(claimable_since)
+    .fetch_all(pool)
+    .await
+    .map_err(LeaderboardError::FetchError)?;
+
+    let matches = sqlx::query_as::<_, Match>(
+        "SELECT Matches.id, StationKeys.name AS station, mode, started_at, ended_at
+        FROM Matches LEFT JOIN StationKeys ON StationKeys.id = Matches.station_id
+        WHERE Matches.id IN (
+            SELECT match_id FROM UnclaimedScores WHERE event_id = ? AND submitted_at > ?
+        )
+        ORDER BY ended_at DESC, Matches.rowid DESC;",
+    )
+    .bind(event_id)
+    .bind(claimable_since)
+    .fetch_all(pool)
+    .await
+    .map_err(LeaderboardError::FetchError)?;
+
+    let mut by_match: HashMap<Uuid, Vec<UnclaimedScoreRow>> = HashMap::new();
+    for score in scores {
+        if let Some(match_id) = score.match_id {
+            by_match.entry(match_id).or_default().push(score);
+        }
+    }
+
+    Ok(matches
+        .into_iter()
+        // scores that got claimed in between the two queries
+        .filter_map(|x| {
+            let scores = by_match.remove(&x.id)?;
+            Some(ClaimListMatch::new(x, scores))
+        })
+        .collect())
+}
+
+/// Takes the scores of every player of a round at once. Either all of them end
+/// up on the claim list or none does.
+///
+/// Responds with the id of the match and the claim ids and codes in the order
+/// of the scores.
+pub async fn submit_match(
+    headers: HeaderMap,
+    State(state): State<Arc<LeaderboardConfig>>,
+    Extension(database): Extension<Arc<Database>>,
+    Extension(live): Extension<Arc<LiveUpdates>>,
+    Extension(event): Extension<Event>,
+    body: Bytes, // put every extractor above this!
+) -> Result<Response, ApiError> {
+    let station = authenticate_submission(&headers, &state, &database, &event, &body).await?;
+
+    let received: ReceivedMatch =
+        serde_json::from_slice(&body).map_err(LeaderboardError::MalformedBody)?;
+    received.validate()?;
+
+    let request_id = idempotency::request_id(&headers, received.request_id.as_deref())?;
+    let fingerprint = idempotency::fingerprint("submit_match", &body);
+    if let Some(request_id) = &request_id {
+        if let Some(response) =
+            idempotency::find(&database.pool, station.id, request_id, &fingerprint).await?
+        {
+            return Ok(json_response(response));
+        }
+    }
+
+    let mut transaction = database
+        .pool
+        .begin()
+        .await
+        .map_err(LeaderboardError::TransactionBeginError)?;
+
+    let inserted = received
+        .insert(&mut transaction, event.id, &station, helper::unix_now())
+        .await?;
+
+    let ids: Vec<String> = inserted.scores.iter().map(|x| x.id.to_string()).collect();
+    let codes: Vec<_> = inserted.scores.iter().map(|x| &x.code).collect();
+    let response = json!({
+        "match_id": inserted.info.id.to_string(),
+        "ids": ids,
+        "codes": codes,
+    })
+    .to_string();
+
+    // a retry overtook us, it already inserted the match
+    if let Some(earlier) = commit_submission(
+        &database,
+        transaction,
+        station.id,
+        request_id.as_deref(),
+        &fingerprint,
+        &response,
+    )
+    .await?
+    {
+        return Ok(json_response(earlier));
+    }
+
+    live.publish_unclaimed(event.id, &inserted);
+
+    Ok(json_response(response))
+}
+
+#[cfg(test)]
+fn received_match(colors: &[&str]) -> ReceivedMatch {
+    ReceivedMatch {
+        scores: colors
+            .iter()
+            .map(|x| RecievedScore {
+                score: 1337,
+                color: x.to_string(),
+                request_id: None,
+            })
+            .collect(),
+        mode: None,
+        started_at: None,
+        ended_at: None,
+        request_id: None,
+    }
+}
+
+#[test]
+fn matches_get_validated() {
+    assert!(received_match(&["#ff0000", "#00ff00"]).validate().is_ok());
+
+    assert!(received_match(&[]).validate().is_err());
+    assert!(received_match(&["#ff0000", "#FF0000"]).validate().is_err());
+    assert!(received_match(&["#ff0000", "red"]).validate().is_err());
+    assert!(received_match(&vec!["#ff0000"; MAX_PLAYERS + 1])
+        .validate()
+        .is_err());
+
+    let mut received = received_match(&["#ff0000"]);
+    received.mode = Some(" ".to_string());
+    assert!(received.validate().is_err());
+}
+
+#[test]
+fn labels_show_time_and_mode() {
+    let info = Match {
+        id: Uuid::nil(),
+        station: None,
+        mode: None,
+        started_at: None,
+        ended_at: 0,
+    };
+    assert_eq!(info.label(), format!("Runde um {}", time_of_day(0)));
+
+    let info = Match {
+        mode: Some("Time Trial".to_string()),
+        started_at: Some(0),
+        ended_at: 120,
+        ..info
+    };
+    assert_eq!(
+        info.label(),
+        format!(
+            "Runde von {} bis {} · Time Trial",
+            time_of_day(0),
+            time_of_day(120)
+        )
+    );
+}
@@ -0,0 +1,275 @@
+use sqlx::SqlitePool;
+
+use crate::{helper::unix_now, LeaderboardError};
+
+struct Migration {
+    version: i64,
+    name: &'static str,
+    sql: &'static str,
+}
+
+/// All schema changes, in the order they have to be applied.
+///
+/// Never edit a migration that was already released, add a new one instead.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial",
+        sql: include_str!("../migrations/0001_initial.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "station_keys",
+        sql: include_str!("../migrations/0002_station_keys.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "used_nonces",
+        sql: include_str!("../migrations/0003_used_nonces.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "claim_expiry",
+        sql: include_str!("../migrations/0004_claim_expiry.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "claimed_scores",
+        sql: include_str!("../migrations/0005_claimed_scores.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "score_submitted_at",
+        sql: include_str!("../migrations/0006_score_submitted_at.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "score_claimed_at",
+        sql: include_str!("../migrations/0007_score_claimed_at.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "events",
+        sql: include_str!("../migrations/0008_events.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "idempotency_keys",
+        sql: include_str!("../migrations/0009_idempotency_keys.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "match_ids",
+        sql: include_str!("../migrations/0010_match_ids.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "matches",
+        sql: include_str!("../migrations/0011_matches.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "claim_codes",
+        sql: include_str!("../migrations/0012_claim_codes.sql"),
+    },
+    Migration {
+        version: 13,
+        name: "raffle_entries",
+        sql: include_str!("../migrations/0013_raffle_entries.sql"),
+    },
+    Migration {
+        version: 14,
+        name: "raffle_outbox",
+        sql: include_str!("../migrations/0014_raffle_outbox.sql"),
+    },
+    Migration {
+        version: 15,
+        name: "forget_delivered_raffle_entries",
+        sql: include_str!("../migrations/0015_forget_delivered_raffle_entries.sql"),
+    },
+    Migration {
+        version: 16,
+        name: "unescape_nicknames",
+        sql: include_str!("../migrations/0016_unescape_nicknames.sql"),
+    },
+    Migration {
+        version: 17,
+        name: "idempotency_fingerprints",
+        sql: include_str!("../migrations/0017_idempotency_fingerprints.sql"),
+    },
+];
+
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map_or(0, |x| x.version)
+}
+
+/// Brings the database up to the newest schema this binary knows about.
+///
+/// Every applied migration is recorded in `SchemaVersion`. A database that has
+/// seen a newer binary is rejected, we would only corrupt it.
+pub async fn run(pool: &SqlitePool) -> Result<(), LeaderboardError> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS SchemaVersion (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at INTEGER NOT NULL
+        );",
+    )
+    .execute(pool)
+    .await
+    .map_err(LeaderboardError::DatabaseSetup)?;
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .map_err(LeaderboardError::DatabaseSetup)?;
+
+    let current: i64 = sqlx::query_scalar("SELECT COALESCE(MAX(version), 0) FROM SchemaVersion;")
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(LeaderboardError::DatabaseSetup)?;
+
+    if current > latest_version() {
+        return Err(LeaderboardError::SchemaTooNew(current));
+    }
+
+    for migration in MIGRATIONS.iter().filter(|x| x.version > current) {
+        sqlx::raw_sql(migration.sql)
+            .execute(&mut *transaction)
+            .await
+            .map_err(|x| LeaderboardError::MigrationFailed(migration.name, x))?;
+
+        sqlx::query("INSERT INTO SchemaVersion (version, name, applied_at) VALUES (?, ?, ?);")
+            .bind(migration.version)
+            .bind(migration.name)
+            .bind(unix_now())
+            .execute(&mut *transaction)
+            .await
+            .map_err(|x| LeaderboardError::MigrationFailed(migration.name, x))?;
+    }
+
+    transaction
+        .commit()
+        .await
+        .map_err(LeaderboardError::DatabaseSetup)?;
+
+    Ok(())
+}
+
+#[test]
+fn migrations_are_ordered() {
+    for pair in MIGRATIONS.windows(2) {
+        assert!(pair[0].version < pair[1].version);
+    }
+}
+
+#[cfg(test)]
+async fn test_pool() -> SqlitePool {
+    sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap()
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn running_migrations_twice_is_harmless() {
+    let pool = test_pool().await;
+
+    assert!(run(&pool).await.is_ok());
+    assert!(run(&pool).await.is_ok());
+
+    let applied: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM SchemaVersion;")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(applied, MIGRATIONS.len() as i64);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn newer_database_gets_rejected() {
+    let pool = test_pool().await;
+
+    assert!(run(&pool).await.is_ok());
+    sqlx::query("INSERT INTO SchemaVersion (version, name, applied_at) VALUES (?, 'future', 0);")
+        .bind(latest_version() + 1)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        run(&pool).await,
+        Err(LeaderboardError::SchemaTooNew(x)) if x == latest_version() + 1
+    ));
+}
+
+/// A database as an older binary that only knew migrations up to `version` left it.
+#[cfg(test)]
+async fn migrate_up_to(pool: &SqlitePool, version: i64) {
+    sqlx::query(
+        "CREATE TABLE SchemaVersion (version INTEGER PRIMARY KEY, name TEXT NOT NULL, applied_at INTEGER NOT NULL);",
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+    for migration in MIGRATIONS.iter().filter(|x| x.version <= version) {
+        sqlx::raw_sql(migration.sql).execute(pool).await.unwrap();
+        sqlx::query("INSERT INTO SchemaVersion (version, name, applied_at) VALUES (?, ?, 0);")
+            .bind(migration.version)
+            .bind(migration.name)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn unclaimed_scores_get_their_own_match() {
+    let pool = test_pool().await;
+
+    // a database from before matches existed
+    migrate_up_to(&pool, 10).await;
+    sqlx::query(
+        "INSERT INTO UnclaimedScores (id, score, color, submitted_at) VALUES (?, 1337, '#123456', 42);",
+    )
+    .bind(uuid::Uuid::new_v4())
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    assert!(run(&pool).await.is_ok());
+
+    let ended_at: i64 = sqlx::query_scalar(
+        "SELECT ended_at FROM Matches JOIN UnclaimedScores ON match_id = Matches.id;",
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(ended_at, 42);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn escaped_nicknames_get_unescaped() {
+    let pool = test_pool().await;
+    migrate_up_to(&pool, 15).await;
+
+    for nickname in ["Tom &amp; Jerry &lt;3", "&quot;&#39;&gt;", "&amp;lt;"] {
+        sqlx::query("INSERT INTO Scores (nickname, score) VALUES (?, 1337);")
+            .bind(nickname)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    assert!(run(&pool).await.is_ok());
+
+    let nicknames: Vec<String> = sqlx::query_scalar("SELECT nickname FROM Scores ORDER BY id;")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(nicknames, ["Tom & Jerry <3", "\"'>", "&lt;"]);
+}
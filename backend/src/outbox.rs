@@ -0,0 +1,372 @@
+use std::{sync::Arc, time::Duration};
+
+use serde_derive::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, SqliteConnection, SqlitePool};
+use tokio::sync::Notify;
+
+use crate::{
+    database::Database,
+    helper::unix_now,
+    raffle::{RaffleEntry, RaffleSink},
+    LeaderboardError,
+};
+
+/// How many entries the worker hands to the sink before it looks for new ones.
+const BATCH_SIZE: i64 = 32;
+
+/// How patiently raffle entries get retried when the raffle sink fails.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutboxConfig {
+    /// failed attempts until an entry is given up on and shows up at `/admin/raffle/failed`
+    pub max_attempts: u32,
+    /// seconds until the first retry, doubled for every further one
+    pub retry_delay: u64,
+    /// upper bound for the delay between two attempts, in seconds
+    pub max_retry_delay: u64,
+    /// seconds between two looks for entries that are due
+    pub interval: u64,
+    /// seconds until a request to the raffle sink counts as failed
+    pub timeout: u64,
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 10,
+            retry_delay: 30,
+            max_retry_delay: 60 * 60,
+            interval: 10,
+            timeout: 30,
+        }
+    }
+}
+
+impl OutboxConfig {
+    pub fn validate(&self) -> Result<(), LeaderboardError> {
+        // failing entries would be due again right away, the worker would hammer the sink
+        if self.retry_delay == 0 {
+            return Err(LeaderboardError::ConfigError(
+                "the raffle outbox needs a retry delay of at least a second".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout.max(1))
+    }
+
+    /// Seconds to wait after the `attempts`th failed attempt.
+    pub fn backoff(&self, attempts: u32) -> u64 {
+        2u64.saturating_pow(attempts.saturating_sub(1))
+            .saturating_mul(self.retry_delay)
+            .min(self.max_retry_delay)
+    }
+}
+
+/// Lets the claim handler wake up the worker, so entries don't wait for the
+/// next interval.
+pub struct Outbox {
+    wake: Notify,
+}
+
+impl Outbox {
+    pub fn new() -> Self {
+        Self {
+            wake: Notify::new(),
+        }
+    }
+
+    pub fn wake(&self) {
+        self.wake.notify_one();
+    }
+}
+
+impl Default for Outbox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(FromRow, Debug)]
+struct PendingEntry {
+    id: i64,
+    entry: String,
+    attempts: u32,
+}
+
+/// An entry the worker gave up on.
+#[derive(Serialize, Debug)]
+pub struct FailedEntry {
+    pub id: i64,
+    pub entry: serde_json::Value,
+    pub attempts: i64,
+    pub last_error: Option<String>,
+    pub created_at: i64,
+    pub failed_at: i64,
+}
+
+#[derive(FromRow)]
+struct FailedRow {
+    id: i64,
+    entry: String,
+    attempts: i64,
+    last_error: Option<String>,
+    created_at: i64,
+    failed_at: i64,
+}
+
+/// Queues `entry` for the raffle sink. Has to happen in the same transaction
+/// as the claim it belongs to.
+pub async fn enqueue(
+    connection: &mut SqliteConnection,
+    entry: &RaffleEntry,
+) -> Result<(), LeaderboardError> {
+    let now = unix_now();
+
+    sqlx::query("INSERT INTO RaffleOutbox (entry, next_attempt_at, created_at) VALUES (?, ?, ?);")
+        .bind(serde_json::to_string(entry).expect("entries always serialize"))
+        .bind(now)
+        .bind(now)
+        .execute(connection)
+        .await
+        .map_err(LeaderboardError::InsertFailure)?;
+
+    Ok(())
+}
+
+/// Hands every entry that is due to `sink`, up to [`BATCH_SIZE`] of them.
+///
+/// Returns how many entries were attempted.
+pub async fn deliver_due(
+    pool: &SqlitePool,
+    sink: &dyn RaffleSink,
+    config: &OutboxConfig,
+) -> Result<usize, LeaderboardError> {
+    let due = sqlx::query_as::<_, PendingEntry>(
+        "SELECT id, entry, attempts FROM RaffleOutbox
+        WHERE failed_at IS NULL AND next_attempt_at <= ?
+        ORDER BY next_attempt_at, id LIMIT ?;",
+    )
+    .bind(unix_now())
+    .bind(BATCH_SIZE)
+    .fetch_all(pool)
+    .await
+    .map_err(LeaderboardError::FetchError)?;
+
+    for pending in &due {
+        let result = match serde_json::from_str::<RaffleEntry>(&pending.entry) {
+            Ok(entry) => sink.submit(&entry).await.map_err(|x| x.to_string()),
+            Err(x) => Err(format!("the stored entry is broken: {x}")),
+        };
+
+        match result {
+            Ok(()) => forget(pool, pending.id).await?,
+            Err(x) => record_failure(pool, pending, &x, config).await?,
+        }
+    }
+
+    Ok(due.len())
+}
+
+/// Delivered entries are the sink's business, the outbox doesn't keep the
+/// personal data around.
+async fn forget(pool: &SqlitePool, id: i64) -> Result<(), LeaderboardError> {
+    sqlx::query("DELETE FROM RaffleOutbox WHERE id = ?;")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(LeaderboardError::DeleteError)?;
+
+    Ok(())
+}
+
+/// Schedules the next attempt, or gives up after `max_attempts`.
+async fn record_failure(
+    pool: &SqlitePool,
+    pending: &PendingEntry,
+    error: &str,
+    config: &OutboxConfig,
+) -> Result<(), LeaderboardError> {
+    let attempts = pending.attempts + 1;
+    let now = unix_now();
+    let failed_at = (attempts >= config.max_attempts).then_some(now);
+
+    sqlx::query(
+        "UPDATE RaffleOutbox SET attempts = ?, last_error = ?, next_attempt_at = ?, failed_at = ?
+        WHERE id = ?;",
+    )
+    .bind(attempts)
+    .bind(error)
+    .bind(now + config.backoff(attempts) as i64)
+    .bind(failed_at)
+    .bind(pending.id)
+    .execute(pool)
+    .await
+    .map_err(LeaderboardError::InsertFailure)?;
+
+    Ok(())
+}
+
+/// Every entry the worker gave up on, oldest first.
+pub async fn failed(pool: &SqlitePool) -> Result<Vec<FailedEntry>, LeaderboardError> {
+    let rows = sqlx::query_as::<_, FailedRow>(
+        "SELECT id, entry, attempts, last_error, created_at, failed_at FROM RaffleOutbox
+        WHERE failed_at IS NOT NULL ORDER BY id;",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(LeaderboardError::FetchError)?;
+
+    Ok(rows
+        .into_iter()
+        .map(|x| FailedEntry {
+            id: x.id,
+            entry: serde_json::from_str(&x.entry).unwrap_or(serde_json::Value::String(x.entry)),
+            attempts: x.attempts,
+            last_error: x.last_error,
+            created_at: x.created_at,
+            failed_at: x.failed_at,
+        })
+        .collect())
+}
+
+/// Gives a failed entry a fresh set of attempts, e.g. once the sink works again.
+pub async fn retry(pool: &SqlitePool, id: i64) -> Result<(), LeaderboardError> {
+    let result = sqlx::query(
+        "UPDATE RaffleOutbox SET attempts = 0, failed_at = NULL, next_attempt_at = ?
+        WHERE id = ? AND failed_at IS NOT NULL;",
+    )
+    .bind(unix_now())
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(LeaderboardError::InsertFailure)?;
+
+    if result.rows_affected() == 0 {
+        return Err(LeaderboardError::UnknownOutboxEntry(id));
+    }
+
+    Ok(())
+}
+
+/// Delivers queued raffle entries for as long as the server runs, right away
+/// when woken up through `outbox` and otherwise every `interval` seconds.
+pub fn spawn(
+    database: Arc<Database>,
+    sink: Arc<dyn RaffleSink>,
+    outbox: Arc<Outbox>,
+    config: OutboxConfig,
+) {
+    tokio::spawn(async move {
+        let interval = Duration::from_secs(config.interval.max(1));
+
+        loop {
+            match deliver_due(&database.pool, sink.as_ref(), &config).await {
+                // there might be more due entries
+                Ok(attempted) if attempted as i64 == BATCH_SIZE => continue,
+                Ok(_) => {}
+                Err(x) => eprintln!("{x}"),
+            }
+
+            let _ = tokio::time::timeout(interval, outbox.wake.notified()).await;
+        }
+    });
+}
+
+#[cfg(test)]
+struct FlakySink {
+    failures: std::sync::atomic::AtomicU32,
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl RaffleSink for FlakySink {
+    async fn submit(&self, _entry: &RaffleEntry) -> Result<(), crate::submission::SubmissionError> {
+        use std::sync::atomic::Ordering;
+
+        if self.failures.load(Ordering::SeqCst) == 0 {
+            return Ok(());
+        }
+
+        self.failures.fetch_sub(1, Ordering::SeqCst);
+        Err(crate::submission::SubmissionError::WriteFailed(
+            "the sink is down".to_string(),
+        ))
+    }
+}
+
+#[test]
+fn backoff_doubles_up_to_the_limit() {
+    let config = OutboxConfig::default();
+
+    assert_eq!(config.backoff(1), 30);
+    assert_eq!(config.backoff(2), 60);
+    assert_eq!(config.backoff(3), 120);
+    assert_eq!(config.backoff(8), 3600);
+    assert_eq!(config.backoff(100), 3600);
+}
+
+#[test]
+fn retries_need_a_delay() {
+    assert!(OutboxConfig::default().validate().is_ok());
+
+    let config = OutboxConfig {
+        retry_delay: 0,
+        ..Default::default()
+    };
+    assert!(config.validate().is_err());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn failing_entries_get_retried_and_given_up_on() {
+    let database = Database::new(&crate::DatabaseConfig::in_memory())
+        .await
+        .unwrap();
+    let pool = &database.pool;
+    let config = OutboxConfig {
+        max_attempts: 2,
+        retry_delay: 0,
+        ..Default::default()
+    };
+
+    let mut connection = pool.acquire().await.unwrap();
+    enqueue(&mut connection, &crate::raffle::entry())
+        .await
+        .unwrap();
+    enqueue(&mut connection, &crate::raffle::entry())
+        .await
+        .unwrap();
+    drop(connection);
+
+    // both entries fail once, then the first one fails again and is given up on
+    let sink = FlakySink { failures: 3.into() };
+    assert_eq!(deliver_due(pool, &sink, &config).await.unwrap(), 2);
+    assert_eq!(deliver_due(pool, &sink, &config).await.unwrap(), 2);
+    assert_eq!(deliver_due(pool, &sink, &config).await.unwrap(), 0);
+
+    let remaining: Vec<i64> = sqlx::query_scalar("SELECT id FROM RaffleOutbox;")
+        .fetch_all(pool)
+        .await
+        .unwrap();
+    assert_eq!(remaining, vec![1], "the delivered entry is gone");
+
+    let given_up = failed(pool).await.unwrap();
+    assert_eq!(given_up.len(), 1);
+    assert_eq!(given_up[0].id, 1);
+    assert_eq!(given_up[0].attempts, 2);
+    assert_eq!(given_up[0].entry["email"], "testy@example.com");
+    assert!(given_up[0]
+        .last_error
+        .as_deref()
+        .unwrap()
+        .contains("the sink is down"));
+
+    retry(pool, 1).await.unwrap();
+    assert!(retry(pool, 1).await.is_err(), "it isn't failed anymore");
+    assert_eq!(deliver_due(pool, &sink, &config).await.unwrap(), 1);
+    assert!(failed(pool).await.unwrap().is_empty());
+}
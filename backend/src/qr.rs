@@ -0,0 +1,120 @@
+use std::{io::Cursor, str::FromStr, sync::Arc};
+
+use axum::{
+    extract::{Path, State},
+    http::header,
+    response::IntoResponse,
+    Extension,
+};
+use image::{ImageFormat, Luma};
+use qrcode::{render::svg, QrCode};
+use uuid::Uuid;
+
+use crate::{
+    database::Database, ensure_claimable, ApiError, ClaimPath, Event, LeaderboardConfig,
+    LeaderboardError,
+};
+
+/// Big enough to scan from a phone held in front of the end screen.
+const MIN_SIZE: u32 = 256;
+
+/// Where the player claims the score `id`, what the QR code points to.
+fn claim_url(state: &LeaderboardConfig, event: &Event, id: Uuid) -> String {
+    format!("{}{}/claim/{id}", state.base_url(), event.prefix())
+}
+
+fn qr_code(url: &str) -> Result<QrCode, LeaderboardError> {
+    QrCode::new(url).map_err(|x| LeaderboardError::QrCodeError(x.to_string()))
+}
+
+pub fn svg(url: &str) -> Result<String, LeaderboardError> {
+    Ok(qr_code(url)?
+        .render::<svg::Color>()
+        .min_dimensions(MIN_SIZE, MIN_SIZE)
+        .build())
+}
+
+pub fn png(url: &str) -> Result<Vec<u8>, LeaderboardError> {
+    let image = qr_code(url)?
+        .render::<Luma<u8>>()
+        .min_dimensions(MIN_SIZE, MIN_SIZE)
+        .build();
+
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|x| LeaderboardError::QrCodeError(x.to_string()))?;
+
+    Ok(png)
+}
+
+/// The claim url of a score that can still be claimed, the QR code would be
+/// useless otherwise.
+async fn claimable_url(
+    state: &LeaderboardConfig,
+    database: &Database,
+    event: &Event,
+    path: &ClaimPath,
+) -> Result<String, LeaderboardError> {
+    let id = Uuid::from_str(&path.id).map_err(|_| LeaderboardError::InvalidId)?;
+
+    ensure_claimable(state, database, event, id).await?;
+
+    Ok(claim_url(state, event, id))
+}
+
+/// For the end screen of the game, which shows it next to the claim code.
+pub async fn claim_qr_svg(
+    State(state): State<Arc<LeaderboardConfig>>,
+    Path(path): Path<ClaimPath>,
+    Extension(database): Extension<Arc<Database>>,
+    Extension(event): Extension<Event>,
+) -> Result<impl IntoResponse, ApiError> {
+    let url = claimable_url(&state, &database, &event, &path).await?;
+
+    Ok(([(header::CONTENT_TYPE, "image/svg+xml")], svg(&url)?))
+}
+
+/// Same as [`claim_qr_svg`], for games that can't draw SVGs.
+pub async fn claim_qr_png(
+    State(state): State<Arc<LeaderboardConfig>>,
+    Path(path): Path<ClaimPath>,
+    Extension(database): Extension<Arc<Database>>,
+    Extension(event): Extension<Event>,
+) -> Result<impl IntoResponse, ApiError> {
+    let url = claimable_url(&state, &database, &event, &path).await?;
+
+    Ok(([(header::CONTENT_TYPE, "image/png")], png(&url)?))
+}
+
+#[test]
+fn qr_codes_render() {
+    let url = "https://leaderboard.example.com/claim/67e55044-10b1-426f-9247-bb680e5fe0c8";
+
+    let svg = svg(url).unwrap();
+    assert!(svg.starts_with("<?xml"));
+    assert!(svg.contains("<svg"));
+
+    let png = png(url).unwrap();
+    assert!(png.starts_with(b"\x89PNG"));
+}
+
+#[test]
+fn qr_codes_point_to_base_url() {
+    let state = LeaderboardConfig {
+        base_url: "https://leaderboard.example.com/".to_string(),
+        ..Default::default()
+    };
+    let event = Event {
+        id: 2,
+        slug: "time-trial".to_string(),
+        name: "Time Trial".to_string(),
+        ordering: None,
+        created_at: 0,
+    };
+
+    assert_eq!(
+        claim_url(&state, &event, Uuid::nil()),
+        "https://leaderboard.example.com/time-trial/claim/00000000-0000-0000-0000-000000000000"
+    );
+}
@@ -0,0 +1,306 @@
+use std::{
+    fs::OpenOptions,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use serde_derive::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::{
+    submission::{HpiFormConfig, HpiFormSink, SubmissionError},
+    LeaderboardError,
+};
+
+/// Everything a player entered to take part in the raffle, together with the
+/// score they claimed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RaffleEntry {
+    /// slug of the event the score belongs to
+    pub event: String,
+    pub score_id: Uuid,
+    pub score: i32,
+    pub firstname: String,
+    pub lastname: String,
+    pub email: String,
+    /// as picked in the claim form, e.g. `school`
+    pub occupation: String,
+    pub newsletter: bool,
+    pub claimed_at: i64,
+}
+
+/// Somewhere raffle entries end up, picked with `[raffle]` in the configuration.
+#[async_trait]
+pub trait RaffleSink: Send + Sync {
+    async fn submit(&self, entry: &RaffleEntry) -> Result<(), SubmissionError>;
+}
+
+/// Which [`RaffleSink`] to use, e.g.
+///
+/// ```toml
+/// [raffle]
+/// sink = "webhook"
+/// url = "https://example.com/raffle"
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(tag = "sink", rename_all = "snake_case", deny_unknown_fields)]
+pub enum RaffleConfig {
+    /// the registration form on the HPI website, see [`HpiFormConfig`]
+    Hpi(Box<HpiFormConfig>),
+    /// the `RaffleEntries` table, to draw the winner later
+    Database,
+    /// POSTs every entry as JSON
+    Webhook { url: String },
+    /// appends every entry as a line to a CSV file
+    Csv { path: PathBuf },
+}
+
+impl Default for RaffleConfig {
+    fn default() -> Self {
+        RaffleConfig::Hpi(Box::default())
+    }
+}
+
+impl RaffleConfig {
+    pub fn validate(&self) -> Result<(), LeaderboardError> {
+        match self {
+            RaffleConfig::Hpi(form) => form.validate(),
+            RaffleConfig::Webhook { url } if url.trim().is_empty() => Err(
+                LeaderboardError::ConfigError("the raffle webhook has no url".to_string()),
+            ),
+            RaffleConfig::Csv { path } if path.as_os_str().is_empty() => Err(
+                LeaderboardError::ConfigError("the raffle csv file has no path".to_string()),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// The sink, its http requests give up after `timeout`.
+    pub fn sink(
+        &self,
+        pool: &SqlitePool,
+        timeout: Duration,
+    ) -> Result<Arc<dyn RaffleSink>, LeaderboardError> {
+        Ok(match self {
+            RaffleConfig::Hpi(form) => Arc::new(HpiFormSink::new(
+                http_client(timeout)?,
+                form.as_ref().clone(),
+            )),
+            RaffleConfig::Database => Arc::new(DatabaseSink { pool: pool.clone() }),
+            RaffleConfig::Webhook { url } => Arc::new(WebhookSink {
+                client: http_client(timeout)?,
+                url: url.clone(),
+            }),
+            RaffleConfig::Csv { path } => Arc::new(CsvSink {
+                path: path.clone(),
+                lock: Mutex::new(()),
+            }),
+        })
+    }
+}
+
+/// A sink that hangs would hold up every entry behind it in the outbox.
+fn http_client(timeout: Duration) -> Result<reqwest::Client, LeaderboardError> {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .map_err(|x| LeaderboardError::ConfigError(format!("couldn't set up the http client: {x}")))
+}
+
+pub struct DatabaseSink {
+    pool: SqlitePool,
+}
+
+#[async_trait]
+impl RaffleSink for DatabaseSink {
+    async fn submit(&self, entry: &RaffleEntry) -> Result<(), SubmissionError> {
+        sqlx::query(
+            "INSERT INTO RaffleEntries
+            (event, score_id, score, firstname, lastname, email, occupation, newsletter, claimed_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?);",
+        )
+        .bind(&entry.event)
+        .bind(entry.score_id)
+        .bind(entry.score)
+        .bind(&entry.firstname)
+        .bind(&entry.lastname)
+        .bind(&entry.email)
+        .bind(&entry.occupation)
+        .bind(entry.newsletter)
+        .bind(entry.claimed_at)
+        .execute(&self.pool)
+        .await
+        .map_err(SubmissionError::StoreFailed)?;
+
+        Ok(())
+    }
+}
+
+pub struct WebhookSink {
+    client: reqwest::Client,
+    url: String,
+}
+
+#[async_trait]
+impl RaffleSink for WebhookSink {
+    async fn submit(&self, entry: &RaffleEntry) -> Result<(), SubmissionError> {
+        let body = serde_json::to_string(entry).expect("entries always serialize");
+
+        self.client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await
+            .and_then(|x| x.error_for_status())
+            .map_err(SubmissionError::SubmitFailed)?;
+
+        Ok(())
+    }
+}
+
+pub struct CsvSink {
+    path: PathBuf,
+    /// one entry at a time, so appends don't interleave
+    lock: Mutex<()>,
+}
+
+/// Appends `entry` to the file at `path`, with a header if the file is new.
+fn append_csv(path: &Path, entry: &RaffleEntry) -> Result<(), SubmissionError> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|x| SubmissionError::WriteFailed(x.to_string()))?;
+    let is_new = file
+        .metadata()
+        .map_err(|x| SubmissionError::WriteFailed(x.to_string()))?
+        .len()
+        == 0;
+
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(is_new)
+        .from_writer(file);
+    writer
+        .serialize(entry)
+        .and_then(|_| writer.flush().map_err(csv::Error::from))
+        .map_err(|x| SubmissionError::WriteFailed(x.to_string()))
+}
+
+#[async_trait]
+impl RaffleSink for CsvSink {
+    async fn submit(&self, entry: &RaffleEntry) -> Result<(), SubmissionError> {
+        let (path, entry) = (self.path.clone(), entry.clone());
+        let _guard = self.lock.lock().await;
+
+        tokio::task::spawn_blocking(move || append_csv(&path, &entry))
+            .await
+            .map_err(|x| SubmissionError::WriteFailed(x.to_string()))?
+    }
+}
+
+/// An entry for the tests of every sink.
+#[cfg(test)]
+pub(crate) fn entry() -> RaffleEntry {
+    RaffleEntry {
+        event: "default".to_string(),
+        score_id: Uuid::nil(),
+        score: 1337,
+        firstname: "Testy".to_string(),
+        lastname: "McTestface, Jr.".to_string(),
+        email: "testy@example.com".to_string(),
+        occupation: "school".to_string(),
+        newsletter: true,
+        claimed_at: 42,
+    }
+}
+
+#[test]
+fn raffle_config_gets_parsed() {
+    let parse = |x: &str| toml::from_str::<RaffleConfig>(x);
+
+    assert_eq!(parse("sink = \"hpi\"").unwrap(), RaffleConfig::default());
+    assert_eq!(
+        parse("sink = \"database\"").unwrap(),
+        RaffleConfig::Database
+    );
+    assert_eq!(
+        parse("sink = \"csv\"\npath = \"raffle.csv\"").unwrap(),
+        RaffleConfig::Csv {
+            path: PathBuf::from("raffle.csv")
+        }
+    );
+    assert!(parse("sink = \"webhook\"").is_err(), "the url is missing");
+    assert!(parse("sink = \"carrier_pigeon\"").is_err());
+    assert!(parse("sink = \"hpi\"\nurl_typo = \"\"").is_err());
+
+    let RaffleConfig::Hpi(form) = parse(
+        "sink = \"hpi\"
+        event_id = 4711
+        [fields]
+        email = \"contactdetails_6[0][identification]\"
+        [occupations]
+        school = \"Schüler\"",
+    )
+    .unwrap() else {
+        panic!("not the hpi form");
+    };
+    assert_eq!(form.event_id, 4711);
+    assert_eq!(form.fields.email, "contactdetails_6[0][identification]");
+    assert_eq!(form.fields.firstname, "persons[0][first_name]");
+    assert_eq!(form.occupations.len(), 1, "the table gets replaced");
+
+    let empty = RaffleConfig::Webhook {
+        url: " ".to_string(),
+    };
+    assert!(empty.validate().is_err());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn csv_sink_appends_entries() {
+    let path = std::env::temp_dir().join(format!("ropeat-raffle-{}.csv", Uuid::new_v4()));
+    let sink = CsvSink {
+        path: path.clone(),
+        lock: Mutex::new(()),
+    };
+
+    sink.submit(&entry()).await.unwrap();
+    sink.submit(&entry()).await.unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let lines: Vec<_> = content.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "event,score_id,score,firstname,lastname,email,occupation,newsletter,claimed_at",
+            "default,00000000-0000-0000-0000-000000000000,1337,Testy,\"McTestface, Jr.\",testy@example.com,school,true,42",
+            "default,00000000-0000-0000-0000-000000000000,1337,Testy,\"McTestface, Jr.\",testy@example.com,school,true,42",
+        ]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn database_sink_stores_entries() {
+    let database = crate::database::Database::new(&crate::DatabaseConfig::in_memory())
+        .await
+        .unwrap();
+    let sink = DatabaseSink {
+        pool: database.pool.clone(),
+    };
+
+    sink.submit(&entry()).await.unwrap();
+
+    let email: String = sqlx::query_scalar("SELECT email FROM RaffleEntries;")
+        .fetch_one(&database.pool)
+        .await
+        .unwrap();
+    assert_eq!(email, "testy@example.com");
+}
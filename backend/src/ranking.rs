@@ -0,0 +1,217 @@
+use std::cmp::Ordering;
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{PlacementScoreRow, ScoreRow};
+
+/// Which scores end up on top of the leaderboard.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum ScoreOrdering {
+    /// points, the classic game mode
+    #[default]
+    HigherIsBetter,
+    /// times, e.g. for time trials
+    LowerIsBetter,
+}
+
+impl ScoreOrdering {
+    /// Sort direction for `ORDER BY score`, best first.
+    pub fn sql(self) -> &'static str {
+        match self {
+            Self::HigherIsBetter => "DESC",
+            Self::LowerIsBetter => "ASC",
+        }
+    }
+
+    /// Compares two scores so that the better one comes first.
+    pub fn compare(self, a: i32, b: i32) -> Ordering {
+        match self {
+            Self::HigherIsBetter => b.cmp(&a),
+            Self::LowerIsBetter => a.cmp(&b),
+        }
+    }
+}
+
+/// How placements are assigned when scores are equal.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RankingStrategy {
+    /// equal scores share a placement, the following ones are skipped ("1224")
+    #[default]
+    Competition,
+    /// equal scores share a placement, nothing is skipped ("1223")
+    Dense,
+    /// every score gets its own placement, ties in order of claiming ("1234")
+    Ordinal,
+    /// ties are broken by submission time, the earlier score places better
+    EarlierSubmission,
+}
+
+/// Submission time for sorting, scores from before it was recorded come last.
+fn submission_key(score: &ScoreRow) -> (bool, i64) {
+    (
+        score.submitted_at.is_none(),
+        score.submitted_at.unwrap_or(0),
+    )
+}
+
+/// Sorts scores best first and assigns placements according to `strategy`.
+///
+/// The sort is stable, scores that come in sorted (by claim order) keep
+/// their order among equals.
+pub fn rank(
+    mut scores: Vec<ScoreRow>,
+    ordering: ScoreOrdering,
+    strategy: RankingStrategy,
+) -> Vec<PlacementScoreRow> {
+    scores.sort_by(|a, b| {
+        let by_score = ordering.compare(a.score, b.score);
+
+        match strategy {
+            RankingStrategy::EarlierSubmission => {
+                by_score.then_with(|| submission_key(a).cmp(&submission_key(b)))
+            }
+            _ => by_score,
+        }
+    });
+
+    let mut placement_scores: Vec<PlacementScoreRow> = Vec::with_capacity(scores.len());
+
+    let mut last: Option<&ScoreRow> = None;
+    let mut last_placement = 0;
+    for (i, score) in scores.iter().enumerate() {
+        let tied = last.is_some_and(|last| match strategy {
+            RankingStrategy::Competition | RankingStrategy::Dense => last.score == score.score,
+            RankingStrategy::Ordinal => false,
+            RankingStrategy::EarlierSubmission => {
+                last.score == score.score && last.submitted_at == score.submitted_at
+            }
+        });
+
+        if !tied {
+            last_placement = match strategy {
+                RankingStrategy::Dense => last_placement + 1,
+                _ => (i + 1).try_into().unwrap(),
+            };
+        }
+        last = Some(score);
+
+        placement_scores.push(PlacementScoreRow {
+            id: score.id,
+            nickname: score.nickname.clone(),
+            score: score.score,
+            placement: last_placement,
+        });
+    }
+
+    placement_scores
+}
+
+#[cfg(test)]
+fn scores(scores: &[i32]) -> Vec<ScoreRow> {
+    scores
+        .iter()
+        .enumerate()
+        .map(|(i, score)| ScoreRow {
+            id: i as i64,
+            nickname: format!("player {i}"),
+            score: *score,
+            submitted_at: Some(1_700_000_000 + i as i64),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+fn placements(ranked: &[PlacementScoreRow]) -> Vec<u32> {
+    ranked.iter().map(|x| x.placement).collect()
+}
+
+#[cfg(test)]
+fn ranked_scores(ranked: &[PlacementScoreRow]) -> Vec<i32> {
+    ranked.iter().map(|x| x.score).collect()
+}
+
+#[cfg(test)]
+fn ids(ranked: &[PlacementScoreRow]) -> Vec<i64> {
+    ranked.iter().map(|x| x.id).collect()
+}
+
+#[cfg(test)]
+fn rank_highest(scores: Vec<ScoreRow>, strategy: RankingStrategy) -> Vec<PlacementScoreRow> {
+    rank(scores, ScoreOrdering::HigherIsBetter, strategy)
+}
+
+#[test]
+fn ties_share_a_placement() {
+    let ranked = rank_highest(scores(&[30, 20, 20, 10]), RankingStrategy::Competition);
+
+    assert_eq!(placements(&ranked), [1, 2, 2, 4]);
+}
+
+#[test]
+fn zero_scores_get_ranked() {
+    let ranked = rank_highest(scores(&[0, 0]), RankingStrategy::Competition);
+
+    assert_eq!(placements(&ranked), [1, 1]);
+}
+
+#[test]
+fn lower_scores_win_when_configured() {
+    let ranked = rank(
+        scores(&[30, 10, 20, 10]),
+        ScoreOrdering::LowerIsBetter,
+        RankingStrategy::Competition,
+    );
+
+    assert_eq!(ranked_scores(&ranked), [10, 10, 20, 30]);
+    assert_eq!(placements(&ranked), [1, 1, 3, 4]);
+}
+
+#[test]
+fn unsorted_scores_get_sorted() {
+    let ranked = rank_highest(scores(&[10, 30, 20]), RankingStrategy::Competition);
+
+    assert_eq!(ranked_scores(&ranked), [30, 20, 10]);
+    assert_eq!(placements(&ranked), [1, 2, 3]);
+}
+
+#[test]
+fn dense_ranking_doesnt_skip_placements() {
+    let ranked = rank_highest(scores(&[30, 20, 20, 10, 10, 5]), RankingStrategy::Dense);
+
+    assert_eq!(placements(&ranked), [1, 2, 2, 3, 3, 4]);
+}
+
+#[test]
+fn ordinal_ranking_keeps_claim_order() {
+    let ranked = rank_highest(scores(&[30, 20, 20, 10]), RankingStrategy::Ordinal);
+
+    assert_eq!(placements(&ranked), [1, 2, 3, 4]);
+    assert_eq!(ids(&ranked), [0, 1, 2, 3]);
+}
+
+#[test]
+fn earlier_submission_wins_ties() {
+    let mut scores = scores(&[20, 20, 30, 20]);
+    // claimed in a different order than submitted
+    scores[0].submitted_at = Some(1_700_000_500);
+
+    let ranked = rank_highest(scores, RankingStrategy::EarlierSubmission);
+
+    assert_eq!(ids(&ranked), [2, 1, 3, 0]);
+    assert_eq!(placements(&ranked), [1, 2, 3, 4]);
+}
+
+#[test]
+fn simultaneous_submissions_share_a_placement() {
+    let mut scores = scores(&[20, 20, 20]);
+    scores[1].submitted_at = scores[0].submitted_at;
+    scores[2].submitted_at = None;
+
+    let ranked = rank_highest(scores, RankingStrategy::EarlierSubmission);
+
+    assert_eq!(ids(&ranked), [0, 1, 2]);
+    assert_eq!(placements(&ranked), [1, 1, 3]);
+}
@@ -0,0 +1,123 @@
+use axum::http::HeaderMap;
+use hmac::{Hmac, Mac};
+use serde_derive::Deserialize;
+use sha2::Sha256;
+use sqlx::SqlitePool;
+
+use crate::{helper::unix_now, LeaderboardError};
+
+pub const TIMESTAMP_HEADER: &str = "x-ropeat-timestamp";
+pub const NONCE_HEADER: &str = "x-ropeat-nonce";
+pub const SIGNATURE_HEADER: &str = "x-ropeat-signature";
+
+/// When configured, every score submission has to be signed by the game.
+///
+/// The signature is the hex encoded HMAC-SHA256 of `{timestamp}\n{nonce}\n{body}`
+/// with the shared `secret`, where `timestamp` is in seconds since the unix epoch.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SigningConfig {
+    pub secret: String,
+    /// how far the timestamp may be off from the server clock, in seconds
+    #[serde(default = "default_max_age")]
+    pub max_age: u64,
+}
+
+fn default_max_age() -> u64 {
+    300
+}
+
+impl SigningConfig {
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self {
+            secret: secret.into(),
+            max_age: default_max_age(),
+        }
+    }
+}
+
+fn mac(secret: &str, timestamp: &str, nonce: &str, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(timestamp.as_bytes());
+    mac.update(b"\n");
+    mac.update(nonce.as_bytes());
+    mac.update(b"\n");
+    mac.update(body);
+    mac
+}
+
+#[cfg(test)]
+fn sign(secret: &str, timestamp: &str, nonce: &str, body: &[u8]) -> String {
+    hex::encode(mac(secret, timestamp, nonce, body).finalize().into_bytes())
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Result<&'a str, LeaderboardError> {
+    headers
+        .get(name)
+        .and_then(|x| x.to_str().ok())
+        .filter(|x| !x.is_empty())
+        .ok_or(LeaderboardError::MissingSignature)
+}
+
+/// Checks signature, timestamp and nonce of a submission, in that order.
+///
+/// The nonce is only remembered once the signature is known to be good,
+/// otherwise anybody could burn nonces of the game.
+pub async fn verify(
+    config: &SigningConfig,
+    headers: &HeaderMap,
+    body: &[u8],
+    pool: &SqlitePool,
+) -> Result<(), LeaderboardError> {
+    let timestamp = header(headers, TIMESTAMP_HEADER)?;
+    let nonce = header(headers, NONCE_HEADER)?;
+    let signature = header(headers, SIGNATURE_HEADER)?;
+
+    let signature = hex::decode(signature).map_err(|_| LeaderboardError::BadSignature)?;
+    mac(&config.secret, timestamp, nonce, body)
+        .verify_slice(&signature)
+        .map_err(|_| LeaderboardError::BadSignature)?;
+
+    let timestamp: i64 = timestamp
+        .parse()
+        .map_err(|_| LeaderboardError::StaleTimestamp)?;
+    let now = unix_now();
+    let max_age = config.max_age as i64;
+
+    if timestamp.abs_diff(now) > config.max_age {
+        return Err(LeaderboardError::StaleTimestamp);
+    }
+
+    // older nonces can't be replayed anyway, their timestamp is stale by now
+    sqlx::query("DELETE FROM UsedNonces WHERE used_at < ?;")
+        .bind(now - 2 * max_age)
+        .execute(pool)
+        .await
+        .map_err(LeaderboardError::DeleteError)?;
+
+    sqlx::query("INSERT INTO UsedNonces (nonce, used_at) VALUES (?, ?);")
+        .bind(nonce)
+        .bind(now)
+        .execute(pool)
+        .await
+        .map_err(|x| match x {
+            sqlx::Error::Database(ref e) if e.is_unique_violation() => {
+                LeaderboardError::ReusedNonce
+            }
+            x => LeaderboardError::InsertFailure(x),
+        })?;
+
+    Ok(())
+}
+
+#[test]
+fn signature_covers_everything() {
+    let signature = sign("secret", "1700000000", "nonce", b"{}");
+
+    assert_eq!(signature.len(), 64);
+    assert_ne!(signature, sign("other", "1700000000", "nonce", b"{}"));
+    assert_ne!(signature, sign("secret", "1700000001", "nonce", b"{}"));
+    assert_ne!(signature, sign("secret", "1700000000", "nonce2", b"{}"));
+    assert_ne!(signature, sign("secret", "1700000000", "nonce", b"{ }"));
+}
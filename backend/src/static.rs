@@ -22,6 +22,20 @@ pub async fn script() -> impl IntoResponse {
     )
 }
 
+pub async fn leaderboard_script() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/javascript")],
+        include_bytes!("../assets/leaderboard.js"),
+    )
+}
+
+pub async fn claim_list_script() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/javascript")],
+        include_bytes!("../assets/claim_list.js"),
+    )
+}
+
 pub async fn font() -> impl IntoResponse {
     (
         [(header::CONTENT_TYPE, "application/octet-stream")],
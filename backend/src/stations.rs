@@ -0,0 +1,121 @@
+use axum::http::{header::AUTHORIZATION, HeaderMap};
+use serde_derive::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::{prelude::FromRow, SqlitePool};
+use uuid::Uuid;
+
+use crate::{helper::unix_now, LeaderboardError};
+
+/// A game PC at the booth, identified by its own API key.
+#[derive(FromRow, Debug, Clone)]
+pub struct Station {
+    pub id: i64,
+    pub name: String,
+    pub event_id: i64,
+}
+
+#[derive(FromRow, Serialize, Debug)]
+pub struct StationKeyInfo {
+    pub name: String,
+    pub created_at: i64,
+    pub revoked_at: Option<i64>,
+}
+
+/// Only the hash of a key is stored, a leaked database doesn't leak the keys.
+fn hash_key(key: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(key))
+}
+
+/// Resolves the `Authorization` header to the station it belongs to.
+///
+/// Callers have to check that the station belongs to the event it submits to.
+pub async fn authenticate(
+    headers: &HeaderMap,
+    pool: &SqlitePool,
+) -> Result<Station, LeaderboardError> {
+    let Some(authorization) = headers.get(AUTHORIZATION) else {
+        return Err(LeaderboardError::MissingAuth);
+    };
+
+    sqlx::query_as::<_, Station>(
+        "SELECT id, name, event_id FROM StationKeys WHERE key_hash = ? AND revoked_at IS NULL;",
+    )
+    .bind(hash_key(authorization.as_bytes()))
+    .fetch_optional(pool)
+    .await
+    .map_err(LeaderboardError::FetchError)?
+    .ok_or(LeaderboardError::WrongAuth)
+}
+
+/// Creates a new key for `name` and returns it. The key can't be recovered later.
+pub async fn create_key(
+    pool: &SqlitePool,
+    event_id: i64,
+    name: &str,
+) -> Result<String, LeaderboardError> {
+    let key = Uuid::new_v4().simple().to_string();
+
+    sqlx::query(
+        "INSERT INTO StationKeys (event_id, name, key_hash, created_at) VALUES (?, ?, ?, ?);",
+    )
+    .bind(event_id)
+    .bind(name)
+    .bind(hash_key(key.as_bytes()))
+    .bind(unix_now())
+    .execute(pool)
+    .await
+    .map_err(|x| match x {
+        sqlx::Error::Database(ref e) if e.is_unique_violation() => {
+            LeaderboardError::StationExists(name.to_string())
+        }
+        x => LeaderboardError::InsertFailure(x),
+    })?;
+
+    Ok(key)
+}
+
+pub async fn revoke_key(
+    pool: &SqlitePool,
+    event_id: i64,
+    name: &str,
+) -> Result<(), LeaderboardError> {
+    let result = sqlx::query(
+        "UPDATE StationKeys SET revoked_at = ?
+        WHERE event_id = ? AND name = ? AND revoked_at IS NULL;",
+    )
+    .bind(unix_now())
+    .bind(event_id)
+    .bind(name)
+    .execute(pool)
+    .await
+    .map_err(LeaderboardError::InsertFailure)?;
+
+    if result.rows_affected() == 0 {
+        return Err(LeaderboardError::UnknownStation(name.to_string()));
+    }
+
+    Ok(())
+}
+
+pub async fn list_keys(
+    pool: &SqlitePool,
+    event_id: i64,
+) -> Result<Vec<StationKeyInfo>, LeaderboardError> {
+    sqlx::query_as::<_, StationKeyInfo>(
+        "SELECT name, created_at, revoked_at FROM StationKeys
+        WHERE event_id = ? ORDER BY created_at, id;",
+    )
+    .bind(event_id)
+    .fetch_all(pool)
+    .await
+    .map_err(LeaderboardError::FetchError)
+}
+
+#[test]
+fn key_hash_is_stable_hex() {
+    let hash = hash_key(b"abcd");
+
+    assert_eq!(hash.len(), 64);
+    assert_eq!(hash, hash_key(b"abcd"));
+    assert_ne!(hash, hash_key(b"abce"));
+}
@@ -1,17 +1,23 @@
-use reqwest::Response;
-use serde::Serialize;
+use std::collections::BTreeMap;
 
-use regex::Regex;
+use async_trait::async_trait;
+use reqwest::Client;
+use scraper::{ElementRef, Html, Selector};
+use serde_derive::Deserialize;
 
-use crate::LeaderboardError;
-
-const HPI_FORM: &str = "https://hpi.de/registrierung/2025/gewinnspiel-gamescom-2025/";
+use crate::{
+    raffle::{RaffleEntry, RaffleSink},
+    LeaderboardError,
+};
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum SubmissionError {
     TokenFetchFailed(reqwest::Error),
-    TokenExtractFailed,
+    TokenExtractFailed(String),
     SubmitFailed(reqwest::Error),
+    StoreFailed(sqlx::Error),
+    WriteFailed(String),
 }
 
 impl std::fmt::Display for SubmissionError {
@@ -23,158 +29,487 @@ impl std::fmt::Display for SubmissionError {
                     "Couldn't fetch access token from HPI Website. Are we blocked? Internal Error: {x}"
                 )
             }
-            Self::TokenExtractFailed => {
+            Self::TokenExtractFailed(x) => {
                 write!(
                     fmt,
-                    "Couldn't extract token from HPI Website. Did the layout change?"
+                    "Couldn't extract the form id from HPI Website. Reason: {x}"
                 )
             }
             Self::SubmitFailed(x) => {
                 write!(fmt, "Couldn't submit data to HPI Website. Are we blocked, was the format changed or did the user enter something malformed? Internal Error: {x}")
             }
+            Self::StoreFailed(x) => {
+                write!(
+                    fmt,
+                    "Couldn't store raffle entry in the database. Internal Error: {x}"
+                )
+            }
+            Self::WriteFailed(x) => {
+                write!(
+                    fmt,
+                    "Couldn't write raffle entry to the file. Internal Error: {x}"
+                )
+            }
         }
     }
 }
 
 impl std::error::Error for SubmissionError {}
 
-#[derive(Debug, Clone, Serialize)]
+/// The registration form on the HPI website, so a changed form can be fixed
+/// in the configuration instead of the code:
+///
+/// ```toml
+/// [raffle]
+/// sink = "hpi"
+/// url = "https://hpi.de/registrierung/2026/gewinnspiel-gamescom-2026/"
+/// event_id = 4711
+///
+/// [raffle.fields]
+/// occupation = "registrationvarchars_107[0][registrationvarchar]"
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct HpiFormConfig {
+    /// page of the form, it is fetched for the hidden fields and posted to
+    pub url: String,
+    /// id of the raffle in the registration system
+    pub event_id: u64,
+    pub fields: HpiFormFields,
+    /// what gets sent for the occupations of the claim form
+    pub occupations: BTreeMap<String, String>,
+    /// what gets sent for an occupation that is missing in `occupations`
+    pub other_occupation: String,
+    pub newsletter_yes: String,
+    pub newsletter_no: String,
+    /// what gets sent for the consent to the data processing
+    pub consent: String,
+}
+
+/// Names of the form fields.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct HpiFormFields {
+    pub firstname: String,
+    pub lastname: String,
+    pub email: String,
+    pub occupation: String,
+    pub newsletter: String,
+    pub consent: String,
+    pub event_id: String,
+    /// hidden field that tells the registration form apart from the others on the page
+    pub form_id: String,
+    pub action: String,
+}
+
+impl Default for HpiFormConfig {
+    fn default() -> Self {
+        Self {
+            url: "https://hpi.de/registrierung/2025/gewinnspiel-gamescom-2025/".to_string(),
+            event_id: 4062,
+            fields: HpiFormFields::default(),
+            occupations: [
+                ("school", "Schüler:in"),
+                ("university", "Student:in"),
+                ("parent", "Elternteil"),
+                ("other", "sonstiges"),
+            ]
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+            other_occupation: "sonstiges".to_string(),
+            newsletter_yes: "yes".to_string(),
+            newsletter_no: "no".to_string(),
+            consent: "Ja, ich stimme zu.".to_string(),
+        }
+    }
+}
+
+impl Default for HpiFormFields {
+    fn default() -> Self {
+        Self {
+            firstname: "persons[0][first_name]".to_string(),
+            lastname: "persons[0][last_name]".to_string(),
+            email: "contactdetails_5[0][identification]".to_string(),
+            occupation: "registrationvarchars_103[0][registrationvarchar]".to_string(),
+            newsletter: "registrationvarchars_105[0][registrationvarchar]".to_string(),
+            consent: "registrationvarchars_106[0][registrationvarchar]".to_string(),
+            event_id: "events_contacts[0][event_id]".to_string(),
+            form_id: "zz_id".to_string(),
+            action: "zz_action".to_string(),
+        }
+    }
+}
+
+impl HpiFormConfig {
+    pub fn validate(&self) -> Result<(), LeaderboardError> {
+        let error = |x: &str| {
+            Err(LeaderboardError::ConfigError(format!(
+                "hpi raffle form: {x}"
+            )))
+        };
+
+        if self.url.trim().is_empty() {
+            return error("the url is empty");
+        }
+
+        let fields = &self.fields;
+        let names = [
+            &fields.firstname,
+            &fields.lastname,
+            &fields.email,
+            &fields.occupation,
+            &fields.newsletter,
+            &fields.consent,
+            &fields.event_id,
+            &fields.form_id,
+            &fields.action,
+        ];
+        if names.iter().any(|x| x.trim().is_empty()) {
+            return error("a field name is empty");
+        }
+
+        Ok(())
+    }
+
+    pub fn form_data(&self, entry: &RaffleEntry) -> HPIFormData {
+        let occupation = self
+            .occupations
+            .get(&entry.occupation)
+            .unwrap_or(&self.other_occupation);
+
+        let email_consent = match entry.newsletter {
+            true => &self.newsletter_yes,
+            false => &self.newsletter_no,
+        };
+
+        HPIFormData {
+            firstname: entry.firstname.clone(),
+            lastname: entry.lastname.clone(),
+            email: entry.email.clone(),
+            occupation: occupation.clone(),
+            email_consent: email_consent.clone(),
+            data_processing_consent: self.consent.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct HPIFormData {
-    #[serde(rename(serialize = "persons[0][first_name]"))]
     pub firstname: String,
-    #[serde(rename(serialize = "persons[0][last_name]"))]
     pub lastname: String,
-    #[serde(rename(serialize = "contactdetails_5[0][identification]"))]
     pub email: String,
-    #[serde(rename(serialize = "registrationvarchars_103[0][registrationvarchar]"))]
     pub occupation: String,
-    #[serde(rename(serialize = "registrationvarchars_105[0][registrationvarchar]"))]
     pub email_consent: String,
-    #[serde(rename(serialize = "registrationvarchars_106[0][registrationvarchar]"))]
     pub data_processing_consent: String,
 }
 
-#[derive(Debug, Clone, Serialize)]
-struct HPIFormDataFinalized {
-    #[serde(flatten)]
-    form_data: HPIFormData,
-    #[serde(rename(serialize = "zz_id"))]
-    id: String,
-    #[serde(rename(serialize = "zz_action"))]
-    action: &'static str, // "insert"
-    #[serde(rename(serialize = "events_contacts[0][event_id]"))]
-    event_id: u64, // 4062
-}
-
-fn create_filled_form(form_data: HPIFormData, id: String) -> HPIFormDataFinalized {
-    HPIFormDataFinalized {
-        form_data,
-        id,
-        action: "insert",
-        event_id: 4062,
+/// The hidden fields of the form on `page` that has a `form_id_field`, in
+/// the order they appear in.
+fn hidden_fields(
+    page: &str,
+    form_id_field: &str,
+) -> Result<Vec<(String, String)>, SubmissionError> {
+    let forms = Selector::parse("form").expect("valid selector");
+    let inputs = Selector::parse("input").expect("valid selector");
+
+    let hidden = |form: ElementRef| -> Vec<(String, String)> {
+        form.select(&inputs)
+            .map(|x| x.value())
+            .filter(|x| {
+                x.attr("type")
+                    .is_some_and(|x| x.eq_ignore_ascii_case("hidden"))
+            })
+            .filter_map(|x| {
+                Some((
+                    x.attr("name")?.to_string(),
+                    x.attr("value").unwrap_or_default().to_string(),
+                ))
+            })
+            .collect()
+    };
+
+    let fields = Html::parse_document(page)
+        .select(&forms)
+        .map(hidden)
+        .find(|x| x.iter().any(|(name, _)| name == form_id_field))
+        .ok_or_else(|| {
+            SubmissionError::TokenExtractFailed(format!(
+                "there is no form with a hidden `{form_id_field}` field"
+            ))
+        })?;
+
+    if fields
+        .iter()
+        .any(|(name, value)| name == form_id_field && value.trim().is_empty())
+    {
+        return Err(SubmissionError::TokenExtractFailed(format!(
+            "the `{form_id_field}` field is empty"
+        )));
     }
+
+    Ok(fields)
 }
 
-async fn send_form(form: HPIFormDataFinalized) -> Result<Response, SubmissionError> {
-    // "post" it
-    let client = reqwest::Client::new();
-    let response = client
-        .post(HPI_FORM)
+/// The form as it gets posted: the hidden fields of the page, and every value
+/// of the player under its configured field name.
+fn create_filled_form(
+    config: &HpiFormConfig,
+    form_data: HPIFormData,
+    hidden: Vec<(String, String)>,
+) -> Vec<(String, String)> {
+    let fields = &config.fields;
+    let values = [
+        (&fields.firstname, form_data.firstname),
+        (&fields.lastname, form_data.lastname),
+        (&fields.email, form_data.email),
+        (&fields.occupation, form_data.occupation),
+        (&fields.newsletter, form_data.email_consent),
+        (&fields.consent, form_data.data_processing_consent),
+        (&fields.action, "insert".to_string()),
+        (&fields.event_id, config.event_id.to_string()),
+    ];
+
+    // our values win over hidden fields with the same name
+    let mut form: Vec<_> = hidden
+        .into_iter()
+        .filter(|(name, _)| values.iter().all(|(x, _)| *x != name))
+        .collect();
+    form.extend(
+        values
+            .into_iter()
+            .map(|(name, value)| (name.clone(), value)),
+    );
+
+    form
+}
+
+async fn send_form(
+    client: &Client,
+    config: &HpiFormConfig,
+    form: Vec<(String, String)>,
+) -> Result<(), SubmissionError> {
+    // "post" it, an error page means the entry didn't make it
+    client
+        .post(&config.url)
         .form(&form)
         .send()
         .await
-        .map_err(|x| SubmissionError::SubmitFailed(x))?;
+        .and_then(|x| x.error_for_status())
+        .map_err(SubmissionError::SubmitFailed)?;
 
-    Ok(response)
+    Ok(())
 }
 
-async fn get_form_id() -> Result<String, SubmissionError> {
-    let re = Regex::new(r#"<input type="hidden" name="zz_id" value="(.{5,10})">"#).unwrap(); // This should never fail!
-
+/// Fetches the form page for its hidden fields, among them the form id.
+async fn get_hidden_fields(
+    client: &Client,
+    config: &HpiFormConfig,
+) -> Result<Vec<(String, String)>, SubmissionError> {
     // "get" html page
-    let client = reqwest::Client::new();
     let response = client
-        .get(HPI_FORM)
+        .get(&config.url)
         .send()
         .await
-        .map_err(|x| SubmissionError::TokenFetchFailed(x))?;
+        .map_err(SubmissionError::TokenFetchFailed)?;
 
-    // "parse" html page
-    let response = response
+    let page = response
         .text()
         .await
-        .map_err(|x| SubmissionError::TokenFetchFailed(x))?;
+        .map_err(SubmissionError::TokenFetchFailed)?;
 
-    // extract token from match
-    let result = re
-        .captures(&response)
-        .ok_or(SubmissionError::TokenExtractFailed)?;
-    let token = result
-        .get(1)
-        .ok_or(SubmissionError::TokenExtractFailed)?
-        .as_str();
+    hidden_fields(&page, &config.fields.form_id)
+}
+
+async fn submit_form(
+    client: &Client,
+    config: &HpiFormConfig,
+    form: HPIFormData,
+) -> Result<(), SubmissionError> {
+    let hidden = get_hidden_fields(client, config).await?;
+    let filled_form = create_filled_form(config, form, hidden);
 
-    Ok(token.to_string())
+    send_form(client, config, filled_form).await?;
+
+    Ok(())
 }
 
-pub async fn submit_form(form: HPIFormData) -> Result<(), SubmissionError> {
-    let id = get_form_id().await?;
-    let filled_form = create_filled_form(form, id);
+/// Enters the player into the raffle through the registration form on the HPI website.
+pub struct HpiFormSink {
+    client: Client,
+    config: HpiFormConfig,
+}
 
-    let response = send_form(filled_form).await?;
+impl HpiFormSink {
+    pub fn new(client: Client, config: HpiFormConfig) -> Self {
+        Self { client, config }
+    }
+}
 
-    println!("{:?}", response);
-    println!("{}", response.text().await.unwrap());
+#[async_trait]
+impl RaffleSink for HpiFormSink {
+    async fn submit(&self, entry: &RaffleEntry) -> Result<(), SubmissionError> {
+        submit_form(&self.client, &self.config, self.config.form_data(entry)).await
+    }
+}
 
-    Ok(())
+#[cfg(test)]
+fn form_data() -> HPIFormData {
+    HPIFormData {
+        firstname: "Testy".to_string(),
+        lastname: "McTestface".to_string(),
+        email: "testy@example.com".to_string(),
+        occupation: "Schüler:in".to_string(),
+        email_consent: "yes".to_string(),
+        data_processing_consent: "Ja, ich stimme zu.".to_string(),
+    }
+}
+
+#[test]
+fn default_form_is_valid() {
+    assert!(HpiFormConfig::default().validate().is_ok());
+
+    let mut config = HpiFormConfig::default();
+    config.fields.form_id = " ".to_string();
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn hidden_fields_come_from_the_registration_form() {
+    let page = include_str!("../tests/fixtures/hpi_form.html");
+
+    assert_eq!(
+        hidden_fields(page, "zz_id").unwrap(),
+        [
+            ("zz_id", "a1b2c3d4"),
+            ("zz_action", "insert"),
+            ("zz_referer", "/registrierung/2025/"),
+            ("events_contacts[0][event_id]", "4062"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+    );
+}
+
+#[test]
+fn hidden_fields_dont_depend_on_the_markup() {
+    let page = include_str!("../tests/fixtures/hpi_form_reordered.html");
+
+    assert_eq!(
+        hidden_fields(page, "zz_id").unwrap(),
+        [
+            ("zz_id", "e5f6g7h8"),
+            ("zz_action", "insert"),
+            ("csrf_token", "tok&en"),
+            ("no_value", ""),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+    );
+}
+
+#[test]
+fn missing_form_id_gets_reported() {
+    let page = include_str!("../tests/fixtures/hpi_form_closed.html");
+
+    assert!(matches!(
+        hidden_fields(page, "zz_id"),
+        Err(SubmissionError::TokenExtractFailed(_))
+    ));
+
+    let empty = r#"<form><input type="hidden" name="zz_id" value=""></form>"#;
+    assert!(hidden_fields(empty, "zz_id").is_err());
+}
+
+#[test]
+fn form_uses_configured_names_and_values() {
+    let mut config = HpiFormConfig {
+        event_id: 4711,
+        ..Default::default()
+    };
+    config.fields.occupation = "registrationvarchars_107[0][registrationvarchar]".to_string();
+
+    let entry = RaffleEntry {
+        occupation: "teacher".to_string(),
+        newsletter: true,
+        ..crate::raffle::entry()
+    };
+    let hidden = vec![
+        ("zz_id".to_string(), "abc123".to_string()),
+        ("zz_action".to_string(), "update".to_string()),
+    ];
+    let form = create_filled_form(&config, config.form_data(&entry), hidden);
+    let value = |name: &str| {
+        form.iter()
+            .find(|(x, _)| x == name)
+            .map(|(_, value)| value.as_str())
+    };
+
+    assert_eq!(
+        value("registrationvarchars_107[0][registrationvarchar]"),
+        Some("sonstiges")
+    );
+    assert_eq!(
+        value("registrationvarchars_103[0][registrationvarchar]"),
+        None
+    );
+    assert_eq!(
+        value("registrationvarchars_105[0][registrationvarchar]"),
+        Some("yes")
+    );
+    assert_eq!(value("events_contacts[0][event_id]"), Some("4711"));
+    assert_eq!(value("zz_id"), Some("abc123"));
+    assert_eq!(value("zz_action"), Some("insert"));
+    assert_eq!(
+        form.iter().filter(|(name, _)| name == "zz_action").count(),
+        1,
+        "the hidden field gets replaced"
+    );
+
+    let entry = RaffleEntry {
+        occupation: "university".to_string(),
+        ..entry
+    };
+    assert_eq!(config.form_data(&entry).occupation, "Student:in");
+
+    let entry = RaffleEntry {
+        occupation: "other".to_string(),
+        ..entry
+    };
+    assert_eq!(config.form_data(&entry).occupation, "sonstiges");
 }
 
 #[cfg(test)]
 #[tokio::test]
 #[ignore = "Makes requests to the HPI website"]
 async fn form_id_works() {
-    assert!(get_form_id().await.is_ok())
+    assert!(get_hidden_fields(&Client::new(), &HpiFormConfig::default())
+        .await
+        .is_ok())
 }
 
 #[cfg(test)]
 #[tokio::test]
 #[ignore = "Makes requests to the HPI website"]
 async fn easy_submission_works() {
-    assert!(submit_form(HPIFormData {
-        firstname: "Testy".to_string(),
-        lastname: "McTestface".to_string(),
-        email: "testy@example.com".to_string(),
-        occupation: "Schüler:in".to_string(),
-        email_consent: "yes".to_string(),
-        data_processing_consent: "Ja, ich stimme zu.".to_string(),
-    })
-    .await
-    .is_ok())
+    assert!(
+        submit_form(&Client::new(), &HpiFormConfig::default(), form_data())
+            .await
+            .is_ok()
+    )
 }
 
 #[cfg(test)]
 #[tokio::test]
 #[ignore = "Makes requests to the HPI website"]
 async fn submission_steps_work() {
-    let token = get_form_id().await;
-    assert!(token.is_ok());
+    let (client, config) = (Client::new(), HpiFormConfig::default());
 
-    let token = token.unwrap();
+    let hidden = get_hidden_fields(&client, &config).await;
+    assert!(hidden.is_ok());
+
+    let hidden = hidden.unwrap();
 
     // create form submission
-    let form = create_filled_form(
-        HPIFormData {
-            firstname: "Testy".to_string(),
-            lastname: "McTestface".to_string(),
-            email: "testy@example.com".to_string(),
-            occupation: "Schüler:in".to_string(),
-            email_consent: "yes".to_string(),
-            data_processing_consent: "Ja, ich stimme zu.".to_string(),
-        },
-        token,
-    );
+    let form = create_filled_form(&config, form_data(), hidden);
 
-    let response = send_form(form).await;
-    println!("{:?}", response);
-    assert!(response.is_ok());
+    assert!(send_form(&client, &config, form).await.is_ok());
 }
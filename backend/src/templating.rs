@@ -1,18 +1,27 @@
 use askama::Template;
 use uuid::Uuid;
 
-use crate::{PlacementScoreRow, UnclaimedScoreRow};
+use crate::{
+    matches::ClaimListMatch, validation::ClaimFormErrors, ClaimScore, PlacementScoreRow,
+    ScoreOrdering,
+};
 
 #[derive(Template)]
-#[template(path = "index.html", escape = "none")]
+#[template(path = "index.html")]
 pub struct LeaderboardTemplate {
+    /// name of the event
+    pub name: String,
     pub scores: Vec<PlacementScoreRow>,
+    pub ordering: ScoreOrdering,
+    /// which part of the event is shown, `None` for all of it
+    pub title: Option<String>,
 }
 
 #[derive(Template)]
 #[template(path = "claim_list.html")]
 pub struct ClaimListTemplate {
-    pub unclaimed_scores: Vec<UnclaimedScoreRow>,
+    /// newest first
+    pub matches: Vec<ClaimListMatch>,
 }
 
 #[derive(Template)]
@@ -20,4 +29,26 @@ pub struct ClaimListTemplate {
 pub struct ClaimFormTemplate {
     pub id: Uuid,
     pub error_message: Option<String>,
+    pub values: ClaimScore,
+    pub errors: ClaimFormErrors,
+}
+
+#[derive(Template)]
+#[template(path = "claim_code.html")]
+pub struct ClaimCodeTemplate {
+    /// what the player typed in, so a typo can be fixed
+    pub code: String,
+    pub code_length: usize,
+    pub error_message: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "already_claimed.html")]
+pub struct AlreadyClaimedTemplate;
+
+#[derive(Template)]
+#[template(path = "error.html")]
+pub struct ErrorTemplate {
+    pub status: u16,
+    pub message: String,
 }
@@ -0,0 +1,163 @@
+use crate::ClaimScore;
+
+pub const MAX_NICKNAME_LENGTH: usize = 32;
+
+/// One message per form field that needs fixing, shown right below the field.
+#[derive(Debug, Default, PartialEq)]
+pub struct ClaimFormErrors {
+    pub nickname: Option<&'static str>,
+    pub email: Option<&'static str>,
+    pub firstname: Option<&'static str>,
+    pub lastname: Option<&'static str>,
+    pub data_protection: Option<&'static str>,
+}
+
+impl ClaimFormErrors {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Good enough to catch typos, the HPI form does the real validation.
+fn looks_like_email(email: &str) -> bool {
+    let Some((local, domain)) = email.split_once('@') else {
+        return false;
+    };
+
+    !local.is_empty()
+        && !domain.contains('@')
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !email.contains(char::is_whitespace)
+}
+
+/// Checks only the parts of the form the player actually opted into.
+pub fn validate_claim(claim: &ClaimScore) -> ClaimFormErrors {
+    let mut errors = ClaimFormErrors::default();
+
+    if claim.leaderboard_checked() {
+        let nickname = claim.nickname.trim();
+
+        if nickname.is_empty() {
+            errors.nickname = Some("Bitte gib ein Pseudonym für das Scoreboard an.");
+        } else if nickname.chars().count() > MAX_NICKNAME_LENGTH {
+            errors.nickname = Some("Dein Pseudonym darf höchstens 32 Zeichen lang sein.");
+        }
+    }
+
+    if claim.raffle_checked() {
+        let email = claim.email.trim();
+
+        if email.is_empty() {
+            errors.email = Some("Bitte gib deine E-Mail-Adresse an.");
+        } else if !looks_like_email(email) {
+            errors.email = Some("Das sieht nicht nach einer gültigen E-Mail-Adresse aus.");
+        }
+
+        if claim.firstname.trim().is_empty() {
+            errors.firstname = Some("Bitte gib deinen Vornamen an.");
+        }
+
+        if claim.lastname.trim().is_empty() {
+            errors.lastname = Some("Bitte gib deinen Nachnamen an.");
+        }
+
+        if claim.data_protection != Some(true) {
+            errors.data_protection =
+                Some("Ohne Zustimmung zum Datenschutz können wir dich nicht eintragen.");
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+fn claim() -> ClaimScore {
+    ClaimScore {
+        wants_leaderboard: Some(true),
+        wants_raffle: Some(true),
+        nickname: "Testy".to_string(),
+        email: "testy@example.com".to_string(),
+        firstname: "Testy".to_string(),
+        lastname: "McTestface".to_string(),
+        newsletter: false,
+        data_protection: Some(true),
+        occupation: "school".to_string(),
+    }
+}
+
+#[test]
+fn complete_claim_is_valid() {
+    assert!(validate_claim(&claim()).is_empty());
+}
+
+#[test]
+fn unchecked_sections_dont_get_validated() {
+    let errors = validate_claim(&ClaimScore {
+        wants_leaderboard: None,
+        wants_raffle: None,
+        nickname: String::new(),
+        email: String::new(),
+        firstname: String::new(),
+        lastname: String::new(),
+        data_protection: None,
+        ..claim()
+    });
+
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn missing_nickname_gets_reported() {
+    let errors = validate_claim(&ClaimScore {
+        nickname: "   ".to_string(),
+        ..claim()
+    });
+
+    assert!(errors.nickname.is_some());
+    assert_eq!(
+        errors,
+        ClaimFormErrors {
+            nickname: errors.nickname,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn long_nickname_gets_reported() {
+    let errors = validate_claim(&ClaimScore {
+        nickname: "x".repeat(MAX_NICKNAME_LENGTH + 1),
+        ..claim()
+    });
+
+    assert!(errors.nickname.is_some());
+}
+
+#[test]
+fn incomplete_raffle_data_gets_reported() {
+    let errors = validate_claim(&ClaimScore {
+        email: "testy@example".to_string(),
+        lastname: String::new(),
+        data_protection: None,
+        ..claim()
+    });
+
+    assert!(errors.nickname.is_none());
+    assert!(errors.email.is_some());
+    assert!(errors.firstname.is_none());
+    assert!(errors.lastname.is_some());
+    assert!(errors.data_protection.is_some());
+}
+
+#[test]
+fn emails_get_checked_loosely() {
+    assert!(looks_like_email("testy@example.com"));
+    assert!(looks_like_email("testy+ropeat@mail.example.com"));
+    assert!(!looks_like_email("testy"));
+    assert!(!looks_like_email("@example.com"));
+    assert!(!looks_like_email("testy@example"));
+    assert!(!looks_like_email("testy@@example.com"));
+    assert!(!looks_like_email("tes ty@example.com"));
+}
@@ -0,0 +1,204 @@
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::LeaderboardError;
+
+/// The predefined windows, a custom one is given with `from` and `to`.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Window {
+    All,
+    Hour,
+    Today,
+}
+
+/// Query parameters selecting which claimed scores a leaderboard covers,
+/// e.g. `?window=today` or `?from=2026-08-20&to=2026-08-21`.
+///
+/// `from` and `to` are either dates (whole days in server time) or RFC 3339 timestamps.
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct WindowQuery {
+    window: Option<Window>,
+    from: Option<String>,
+    to: Option<String>,
+}
+
+/// Claim times in seconds since the unix epoch, `from` inclusive and `to` exclusive.
+#[derive(Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TimeRange {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<i64>,
+}
+
+fn invalid(message: impl Into<String>) -> LeaderboardError {
+    LeaderboardError::InvalidWindow(message.into())
+}
+
+/// Start of `date` in server time, or whatever comes closest on DST changes.
+fn start_of_day(date: NaiveDate) -> Result<i64, LeaderboardError> {
+    let midnight = date.and_hms_opt(0, 0, 0).expect("midnight exists");
+
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|x| x.timestamp())
+        .ok_or_else(|| invalid(format!("{date} has no midnight")))
+}
+
+/// A date as `from` means its start, as `to` its end, so both days are included.
+fn parse_bound(value: &str, end: bool) -> Result<i64, LeaderboardError> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let date = if end {
+            date.succ_opt()
+                .ok_or_else(|| invalid("date too far in the future"))?
+        } else {
+            date
+        };
+
+        return start_of_day(date);
+    }
+
+    DateTime::parse_from_rfc3339(value)
+        .map(|x| x.timestamp())
+        .map_err(|_| {
+            invalid(format!(
+                "`{value}` is neither a date nor an RFC 3339 timestamp"
+            ))
+        })
+}
+
+impl WindowQuery {
+    /// Resolves the window relative to `now`, so `today` moves on at midnight.
+    pub fn range(&self, now: DateTime<Local>) -> Result<TimeRange, LeaderboardError> {
+        let custom = self.from.is_some() || self.to.is_some();
+
+        match self.window {
+            Some(Window::Hour | Window::Today) if custom => {
+                Err(invalid("`window` can't be combined with `from` and `to`"))
+            }
+            Some(Window::Hour) => Ok(TimeRange {
+                from: Some(now.timestamp() - 60 * 60),
+                to: None,
+            }),
+            Some(Window::Today) => Ok(TimeRange {
+                from: Some(start_of_day(now.date_naive())?),
+                to: None,
+            }),
+            Some(Window::All) | None => {
+                let range = TimeRange {
+                    from: self
+                        .from
+                        .as_deref()
+                        .map(|x| parse_bound(x, false))
+                        .transpose()?,
+                    to: self
+                        .to
+                        .as_deref()
+                        .map(|x| parse_bound(x, true))
+                        .transpose()?,
+                };
+
+                if let (Some(from), Some(to)) = (range.from, range.to) {
+                    if from >= to {
+                        return Err(invalid("`from` has to be before `to`"));
+                    }
+                }
+
+                Ok(range)
+            }
+        }
+    }
+
+    /// Heading for the rendered leaderboard, `None` for the all-time leaderboard.
+    pub fn title(&self, range: TimeRange) -> Option<String> {
+        let format = |x: i64| {
+            Local
+                .timestamp_opt(x, 0)
+                .single()
+                .map(|x| x.format("%d.%m.%Y %H:%M").to_string())
+                .unwrap_or_default()
+        };
+
+        match (self.window, range.from, range.to) {
+            (Some(Window::Hour), _, _) => Some("Best of the last hour".to_string()),
+            (Some(Window::Today), _, _) => Some("Best of today".to_string()),
+            (_, None, None) => None,
+            (_, Some(from), None) => Some(format!("Best since {}", format(from))),
+            (_, None, Some(to)) => Some(format!("Best until {}", format(to))),
+            (_, Some(from), Some(to)) => {
+                Some(format!("Best from {} to {}", format(from), format(to)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+fn query(window: Option<Window>, from: Option<&str>, to: Option<&str>) -> WindowQuery {
+    WindowQuery {
+        window,
+        from: from.map(str::to_string),
+        to: to.map(str::to_string),
+    }
+}
+
+#[test]
+fn no_window_covers_everything() {
+    let range = WindowQuery::default().range(Local::now()).unwrap();
+
+    assert_eq!(range, TimeRange::default());
+    assert_eq!(WindowQuery::default().title(range), None);
+}
+
+#[test]
+fn hour_and_today_are_relative_to_now() {
+    let now = Local::now();
+
+    let hour = query(Some(Window::Hour), None, None).range(now).unwrap();
+    assert_eq!(hour.from, Some(now.timestamp() - 3600));
+    assert_eq!(hour.to, None);
+
+    let today = query(Some(Window::Today), None, None).range(now).unwrap();
+    let from = Local.timestamp_opt(today.from.unwrap(), 0).unwrap();
+    assert_eq!(from.date_naive(), now.date_naive());
+    assert_eq!(from.format("%H:%M").to_string(), "00:00");
+}
+
+#[test]
+fn custom_dates_include_both_days() {
+    let range = query(None, Some("2026-08-20"), Some("2026-08-21"))
+        .range(Local::now())
+        .unwrap();
+
+    let day = |x: &str| start_of_day(NaiveDate::parse_from_str(x, "%Y-%m-%d").unwrap());
+    assert_eq!(range.from, day("2026-08-20").ok());
+    assert_eq!(range.to, day("2026-08-22").ok());
+}
+
+#[test]
+fn custom_timestamps_are_exact() {
+    let range = query(
+        None,
+        Some("2026-08-20T10:00:00Z"),
+        Some("2026-08-20T14:00:00+02:00"),
+    )
+    .range(Local::now())
+    .unwrap();
+
+    assert_eq!(range.from, Some(1787220000));
+    assert_eq!(range.to, Some(1787220000 + 2 * 3600));
+}
+
+#[test]
+fn invalid_windows_get_rejected() {
+    let now = Local::now();
+
+    assert!(query(None, Some("yesterday"), None).range(now).is_err());
+    assert!(query(None, Some("2026-08-21"), Some("2026-08-20"))
+        .range(now)
+        .is_err());
+    assert!(query(Some(Window::Today), Some("2026-08-20"), None)
+        .range(now)
+        .is_err());
+}
@@ -1,13 +1,27 @@
-use std::str::FromStr;
+use std::{
+    ops::Deref,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use axum_test::TestServer;
-use backend::routes;
+use backend::{
+    routes, DatabaseConfig, HpiFormConfig, LeaderboardConfig, OutboxConfig, RaffleConfig,
+    ScoreOrdering, SigningConfig,
+};
+use hmac::{Hmac, Mac};
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-const GOOD_TOKEN: &str = "asdf";
+const ADMIN_TOKEN: &str = "asdf";
 const BAD_TOKEN: &str = "asdf1";
+const STATION: &str = "booth-1";
+const SIGNING_SECRET: &str = "shared secret";
 
 const GOOD_SCORE: i32 = 1337;
 const GOOD_SCORE_2: i32 = 13337;
@@ -19,7 +33,7 @@ const NORMAL_NICKNAME_2: &str = "BYE_TESTING!";
 
 const BAD_NICKNAME: &str = "<script>alert(\"'&1'\");</script>";
 const SANITIZED_NICKNAME: &str =
-    "&lt;script&gt;alert(&quot;&#39;&amp;1&#39;&quot;);&lt;/script&gt;";
+    "&#60;script&#62;alert(&#34;&#39;&#38;1&#39;&#34;);&#60;/script&#62;";
 
 #[derive(Serialize)]
 struct GoodScoreFormat {
@@ -57,11 +71,71 @@ struct SubmitResponse {
     id: String,
 }
 
-async fn setup_server() -> TestServer {
-    TestServer::new(routes(GOOD_TOKEN).await.unwrap()).unwrap()
+#[derive(Deserialize)]
+struct NewStationResponse {
+    key: String,
+}
+
+/// A running server together with the key of one registered game station.
+struct TestBooth {
+    server: TestServer,
+    key: String,
+}
+
+impl Deref for TestBooth {
+    type Target = TestServer;
+
+    fn deref(&self) -> &TestServer {
+        &self.server
+    }
+}
+
+fn test_config(database: DatabaseConfig) -> LeaderboardConfig {
+    LeaderboardConfig {
+        admin_token: ADMIN_TOKEN.to_string(),
+        database,
+        // most tests claim through the list
+        public_claim_list: true,
+        ..Default::default()
+    }
+}
+
+async fn setup_server() -> TestBooth {
+    setup_server_with_database(DatabaseConfig::in_memory()).await
+}
+
+async fn setup_server_with_database(database: DatabaseConfig) -> TestBooth {
+    setup_server_with_config(test_config(database)).await
+}
+
+async fn setup_server_with_config(config: LeaderboardConfig) -> TestBooth {
+    let server = start_server(config).await;
+    let key = add_station(&server, STATION).await;
+
+    TestBooth { server, key }
+}
+
+async fn start_server(config: LeaderboardConfig) -> TestServer {
+    TestServer::new(routes(config).await.unwrap()).unwrap()
+}
+
+async fn add_station(server: &TestServer, name: &str) -> String {
+    let response = server
+        .post("/admin/keys")
+        .authorization(ADMIN_TOKEN)
+        .json(&serde_json::json!({"name": name}))
+        .await;
+
+    response.assert_status(StatusCode::CREATED);
+    response.json::<NewStationResponse>().key
+}
+
+fn temporary_database() -> DatabaseConfig {
+    let path = std::env::temp_dir().join(format!("ropeat-{}.db", Uuid::new_v4()));
+    DatabaseConfig::file(path)
 }
 
-async fn submit_score(server: &TestServer) -> Uuid {
+async fn submit_score(server: &TestBooth) -> Uuid {
     let payload = GoodScoreFormat {
         score: GOOD_SCORE,
         color: GOOD_COLOR.to_string(),
@@ -69,7 +143,7 @@ async fn submit_score(server: &TestServer) -> Uuid {
 
     let response = server
         .post("/backend/submit_score")
-        .authorization(GOOD_TOKEN)
+        .authorization(&server.key)
         .json(&payload)
         .await;
 
@@ -79,7 +153,7 @@ async fn submit_score(server: &TestServer) -> Uuid {
     Uuid::from_str(&json.id).unwrap()
 }
 
-async fn submit_score2(server: &TestServer) -> Uuid {
+async fn submit_score2(server: &TestBooth) -> Uuid {
     let payload = GoodScoreFormat {
         score: GOOD_SCORE_2,
         color: GOOD_COLOR_2.to_string(),
@@ -87,7 +161,7 @@ async fn submit_score2(server: &TestServer) -> Uuid {
 
     let response = server
         .post("/backend/submit_score")
-        .authorization(GOOD_TOKEN)
+        .authorization(&server.key)
         .json(&payload)
         .await;
 
@@ -97,11 +171,11 @@ async fn submit_score2(server: &TestServer) -> Uuid {
     Uuid::from_str(&json.id).unwrap()
 }
 
-async fn claim_score(server: &TestServer) {
+async fn claim_score(server: &TestBooth) {
     claim_score_username(server, NORMAL_NICKNAME).await
 }
 
-async fn claim_score_2(server: &TestServer) {
+async fn claim_score_2(server: &TestBooth) {
     let id = submit_score2(server).await;
 
     let body = GoodFormSubmitFormat {
@@ -121,7 +195,7 @@ async fn claim_score_2(server: &TestServer) {
     response.assert_status_in_range(300..400);
 }
 
-async fn claim_score_username(server: &TestServer, nickname: &str) {
+async fn claim_score_username(server: &TestBooth, nickname: &str) {
     let id = submit_score(server).await;
 
     let body = GoodFormSubmitFormat {
@@ -164,7 +238,7 @@ async fn submitted_but_unclaimed_score_shows_on_list() {
 
     let response = server
         .post("/backend/submit_score")
-        .authorization(GOOD_TOKEN)
+        .authorization(&server.key)
         .json(&payload)
         .await;
 
@@ -265,88 +339,1671 @@ async fn same_scores_get_same_position() {
 }
 
 #[tokio::test]
-async fn cant_submit_score_without_token() {
-    let server = setup_server().await;
+async fn scores_survive_a_restart() {
+    // when the server restarts with the same database file, claimed and unclaimed scores are still there
+    let database = temporary_database();
 
-    let payload = GoodScoreFormat {
-        score: GOOD_SCORE,
-        color: GOOD_COLOR.to_string(),
+    let server = setup_server_with_database(database.clone()).await;
+    claim_score(&server).await;
+    submit_score2(&server).await;
+    let TestBooth { server, key } = server;
+    drop(server);
+
+    // the station key survives the restart as well
+    let server = TestBooth {
+        server: start_server(test_config(database.clone())).await,
+        key,
     };
+    submit_score(&server).await;
 
-    let response = server.post("/backend/submit_score").json(&payload).await;
+    let response = server.get("/").await;
+    response.assert_status_ok();
+    response.assert_text_contains(NORMAL_NICKNAME);
+    response.assert_text_contains(GOOD_SCORE.to_string());
 
-    response.assert_status_not_ok();
+    let response = server.get("/claim/list").await;
+    response.assert_status_ok();
+    response.assert_text_contains(GOOD_SCORE_2.to_string());
+
+    let _ = std::fs::remove_file(database.path.unwrap());
 }
 
 #[tokio::test]
-async fn cant_submit_score_with_wrong_token() {
-    let server = setup_server().await;
+async fn claim_redirects_to_configured_base_url() {
+    let server = setup_server_with_config(LeaderboardConfig {
+        base_url: "https://leaderboard.example.com/".to_string(),
+        ..test_config(DatabaseConfig::in_memory())
+    })
+    .await;
 
-    let payload = GoodScoreFormat {
-        score: GOOD_SCORE,
-        color: GOOD_COLOR.to_string(),
+    let id = submit_score(&server).await;
+
+    let body = GoodFormSubmitFormat {
+        wants_leaderboard: Some(true),
+        wants_raffle: None,
+
+        nickname: NORMAL_NICKNAME.to_string(),
+        email: "".to_string(),
+        firstname: "".to_string(),
+        lastname: "".to_owned(),
+        newsletter: false,
+        data_protection: None,
+        occupation: "".to_string(),
     };
 
+    let response = server.post(&format!("/claim/{id}")).form(&body).await;
+    response.assert_status(StatusCode::SEE_OTHER);
+    response.assert_header("location", "https://leaderboard.example.com/claim/list");
+}
+
+#[tokio::test]
+async fn expired_scores_cant_be_claimed() {
+    let server = setup_server_with_config(LeaderboardConfig {
+        claim_ttl: 0,
+        ..test_config(DatabaseConfig::in_memory())
+    })
+    .await;
+
+    let id = submit_score(&server).await;
+
+    let response = server.get("/claim/list").await;
+    response.assert_status_ok();
+    assert!(
+        !response.text().contains(&GOOD_SCORE.to_string()),
+        "The expired score shows up!"
+    );
+
+    let response = server.get(&format!("/claim/{id}")).await;
+    response.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[derive(Deserialize)]
+struct CodeResponse {
+    id: Uuid,
+    code: String,
+}
+
+async fn submit_score_for_code(server: &TestBooth) -> CodeResponse {
     let response = server
         .post("/backend/submit_score")
-        .authorization(BAD_TOKEN)
-        .json(&payload)
+        .authorization(&server.key)
+        .json(&GoodScoreFormat {
+            score: GOOD_SCORE,
+            color: GOOD_COLOR.to_string(),
+        })
         .await;
 
-    response.assert_status_not_ok();
+    response.assert_status_ok();
+    response.json()
 }
 
 #[tokio::test]
-async fn bad_submitted_score_doesnt_show_on_unclaimed_list() {
-    // when uploading a score from the game with an invalid token, this score is not present in the unclaimed scores list
+async fn scores_can_be_claimed_with_their_code() {
     let server = setup_server().await;
 
-    let payload = GoodScoreFormat {
-        score: GOOD_SCORE,
-        color: GOOD_COLOR.to_string(),
-    };
+    let submitted = submit_score_for_code(&server).await;
+    assert_eq!(submitted.code.len(), 6);
+
+    let response = server.get("/claim").await;
+    response.assert_status_ok();
+    response.assert_text_contains("name=\"code\"");
+
+    // players type whatever they like
+    let typed = format!(
+        "{}-{}",
+        &submitted.code[..3].to_lowercase(),
+        &submitted.code[3..]
+    );
+    let response = server.get("/claim").add_query_param("code", typed).await;
+    response.assert_status(StatusCode::SEE_OTHER);
+    response.assert_header(
+        "location",
+        format!("http://localhost:3000/claim/{}", submitted.id),
+    );
 
+    let response = server.get("/claim").add_query_param("code", "ZZZZZZ").await;
+    response.assert_status(StatusCode::NOT_FOUND);
+    response.assert_text_contains("ZZZZZZ");
+
+    // claimed scores don't have a code anymore
     let response = server
-        .post("/backend/submit_score")
-        .authorization(BAD_TOKEN)
-        .json(&payload)
+        .post(&format!("/claim/{}", submitted.id))
+        .form(&leaderboard_claim(NORMAL_NICKNAME))
         .await;
+    response.assert_status(StatusCode::SEE_OTHER);
 
-    response.assert_status_not_ok();
+    let response = server
+        .get("/claim")
+        .add_query_param("code", &submitted.code)
+        .await;
+    response.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn hidden_claim_list_only_allows_codes() {
+    let server = setup_server_with_config(LeaderboardConfig {
+        public_claim_list: false,
+        ..test_config(DatabaseConfig::in_memory())
+    })
+    .await;
+
+    let submitted = submit_score_for_code(&server).await;
 
     let response = server.get("/claim/list").await;
+    response.assert_status(StatusCode::SEE_OTHER);
+    response.assert_header("location", "http://localhost:3000/claim");
+
+    let response = server.get("/events/claims").await;
+    response.assert_status(StatusCode::NOT_FOUND);
+
+    let response = server
+        .get("/claim")
+        .add_query_param("code", &submitted.code)
+        .await;
+    response.assert_status(StatusCode::SEE_OTHER);
+
+    // the next player gets the code page as well
+    let response = server
+        .post(&format!("/claim/{}", submitted.id))
+        .form(&leaderboard_claim(NORMAL_NICKNAME))
+        .await;
+    response.assert_header("location", "http://localhost:3000/claim");
+}
+
+#[tokio::test]
+async fn claimable_scores_have_qr_codes() {
+    let server = setup_server().await;
+    let id = submit_score(&server).await;
 
+    let response = server.get(&format!("/claim/{id}/qr.svg")).await;
     response.assert_status_ok();
-    assert!(
-        !response.text().contains(&GOOD_SCORE.to_string()),
-        "The score shows up!"
+    response.assert_header("content-type", "image/svg+xml");
+    response.assert_text_contains("<svg");
+
+    let response = server.get(&format!("/claim/{id}/qr.png")).await;
+    response.assert_status_ok();
+    response.assert_header("content-type", "image/png");
+    assert!(response.as_bytes().starts_with(b"\x89PNG"));
+
+    let response = server
+        .get(&format!("/claim/{}/qr.svg", Uuid::new_v4()))
+        .await;
+    response.assert_status(StatusCode::NOT_FOUND);
+
+    let response = server.get("/claim/not-a-uuid/qr.png").await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn routes_refuse_empty_token() {
+    let config = LeaderboardConfig {
+        database: DatabaseConfig::in_memory(),
+        ..Default::default()
+    };
+
+    assert!(routes(config).await.is_err());
+}
+
+#[tokio::test]
+async fn api_returns_ranked_leaderboard() {
+    let server = setup_server().await;
+
+    claim_score(&server).await;
+    claim_score(&server).await;
+    claim_score_2(&server).await;
+
+    let response = server.get("/api/leaderboard").await;
+
+    response.assert_status_ok();
+    response.assert_json(&serde_json::json!({
+        "scores": [
+            {"nickname": NORMAL_NICKNAME_2, "score": GOOD_SCORE_2, "placement": 1},
+            {"nickname": NORMAL_NICKNAME, "score": GOOD_SCORE, "placement": 2},
+            {"nickname": NORMAL_NICKNAME, "score": GOOD_SCORE, "placement": 2},
+        ],
+        "ordering": "higher_is_better",
+        "total": 3,
+        "limit": 50,
+        "offset": 0,
+    }));
+}
+
+#[tokio::test]
+async fn lower_scores_win_when_configured() {
+    let server = setup_server_with_config(LeaderboardConfig {
+        ordering: ScoreOrdering::LowerIsBetter,
+        ..test_config(DatabaseConfig::in_memory())
+    })
+    .await;
+
+    claim_score_2(&server).await;
+    claim_score(&server).await;
+
+    let response = server.get("/api/leaderboard").await;
+
+    response.assert_status_ok();
+    response.assert_json(&serde_json::json!({
+        "scores": [
+            {"nickname": NORMAL_NICKNAME, "score": GOOD_SCORE, "placement": 1},
+            {"nickname": NORMAL_NICKNAME_2, "score": GOOD_SCORE_2, "placement": 2},
+        ],
+        "ordering": "lower_is_better",
+        "total": 2,
+        "limit": 50,
+        "offset": 0,
+    }));
+
+    let response = server.get("/").await;
+    let text = response.text();
+
+    assert!(text.contains("Lowest score wins!"));
+    assert!(text.find(NORMAL_NICKNAME).unwrap() < text.find(NORMAL_NICKNAME_2).unwrap());
+}
+
+#[tokio::test]
+async fn windowed_leaderboards_only_count_scores_claimed_within() {
+    let database = temporary_database();
+    let path = database.path.clone().unwrap();
+    let server = setup_server_with_database(database).await;
+
+    claim_score(&server).await;
+    claim_score_2(&server).await;
+
+    // pretend the better score was claimed two days ago
+    let two_days_ago = chrono::Local::now() - chrono::Duration::days(2);
+    let pool = sqlx::SqlitePool::connect(&format!("sqlite://{}", path.display()))
+        .await
+        .unwrap();
+    sqlx::query("UPDATE Scores SET claimed_at = ? WHERE score = ?;")
+        .bind(two_days_ago.timestamp())
+        .bind(GOOD_SCORE_2)
+        .execute(&pool)
+        .await
+        .unwrap();
+    pool.close().await;
+
+    let nicknames = |response: axum_test::TestResponse| -> Vec<String> {
+        response.assert_status_ok();
+        response.json::<serde_json::Value>()["scores"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|x| x["nickname"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let all = server.get("/api/leaderboard").await;
+    assert_eq!(nicknames(all), [NORMAL_NICKNAME_2, NORMAL_NICKNAME]);
+
+    for window in ["hour", "today"] {
+        let response = server
+            .get("/api/leaderboard")
+            .add_query_param("window", window)
+            .await;
+        assert_eq!(nicknames(response), [NORMAL_NICKNAME]);
+    }
+
+    let day = two_days_ago.format("%Y-%m-%d").to_string();
+    let response = server
+        .get("/api/leaderboard")
+        .add_query_param("from", &day)
+        .add_query_param("to", &day)
+        .await;
+    assert_eq!(nicknames(response), [NORMAL_NICKNAME_2]);
+
+    let response = server.get("/").add_query_param("window", "today").await;
+    response.assert_status_ok();
+    response.assert_text_contains("Best of today");
+    response.assert_text_contains(NORMAL_NICKNAME);
+    assert!(!response.text().contains(NORMAL_NICKNAME_2));
+
+    let response = server
+        .get("/api/leaderboard")
+        .add_query_param("from", "yesterday")
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn api_leaderboard_gets_paginated() {
+    let server = setup_server().await;
+
+    claim_score(&server).await;
+    claim_score(&server).await;
+    claim_score_2(&server).await;
+
+    let response = server
+        .get("/api/leaderboard")
+        .add_query_param("limit", 1)
+        .add_query_param("offset", 2)
+        .await;
+
+    response.assert_status_ok();
+    response.assert_json(&serde_json::json!({
+        "scores": [
+            {"nickname": NORMAL_NICKNAME, "score": GOOD_SCORE, "placement": 2},
+        ],
+        "ordering": "higher_is_better",
+        "total": 3,
+        "limit": 1,
+        "offset": 2,
+    }));
+
+    let response = server
+        .get("/api/leaderboard")
+        .add_query_param("limit", 1000)
+        .await;
+    assert_eq!(response.json::<serde_json::Value>()["limit"], 100);
+}
+
+/// Streaming responses need a real socket, the mock transport waits for the whole body.
+async fn setup_http_server() -> TestBooth {
+    let app = routes(test_config(DatabaseConfig::in_memory()))
+        .await
+        .unwrap();
+    let server = TestServer::builder().http_transport().build(app).unwrap();
+    let key = add_station(&server, STATION).await;
+
+    TestBooth { server, key }
+}
+
+/// Reads the stream until the next event called `name` and returns its data.
+async fn next_event(response: &mut reqwest::Response, name: &str) -> serde_json::Value {
+    let mut buffer = String::new();
+    let event = format!("event: {name}\n");
+
+    loop {
+        let chunk = tokio::time::timeout(Duration::from_secs(5), response.chunk())
+            .await
+            .expect("no event arrived in time")
+            .unwrap()
+            .expect("stream ended early");
+        buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+
+        while let Some((message, rest)) = buffer.split_once("\n\n") {
+            if message.contains(&event) {
+                let data = message.lines().find_map(|x| x.strip_prefix("data: "));
+                return serde_json::from_str(data.unwrap()).unwrap();
+            }
+
+            buffer = rest.to_string();
+        }
+    }
+}
+
+#[tokio::test]
+async fn claims_get_pushed_to_live_leaderboard() {
+    let server = setup_http_server().await;
+    claim_score_2(&server).await;
+
+    let url = server.server_address().unwrap().join("events/leaderboard");
+    let mut events = reqwest::get(url.unwrap()).await.unwrap();
+    assert_eq!(events.status(), StatusCode::OK);
+    assert_eq!(events.headers()["content-type"], "text/event-stream");
+
+    claim_score(&server).await;
+
+    let update = next_event(&mut events, "leaderboard").await;
+    assert_eq!(
+        update,
+        serde_json::json!({
+            "scores": [
+                {"nickname": NORMAL_NICKNAME_2, "score": GOOD_SCORE_2, "placement": 1},
+                {"nickname": NORMAL_NICKNAME, "score": GOOD_SCORE, "placement": 2},
+            ],
+            "ordering": "higher_is_better",
+            "highlight": 1,
+        })
     );
 }
 
 #[tokio::test]
-#[ignore = "not implemented"]
-async fn double_claim_score_doesnt_work() {
-    // when submitting a claim form twice for the same score, the second one doesn't work
-    todo!()
+async fn claim_list_gets_live_updates() {
+    let server = setup_http_server().await;
+
+    let url = server.server_address().unwrap().join("events/claims");
+    let mut events = reqwest::get(url.unwrap()).await.unwrap();
+    assert_eq!(events.status(), StatusCode::OK);
+
+    let id = submit_score(&server).await;
+
+    // every score arrives as part of its match
+    let added = next_event(&mut events, "added").await;
+    assert_eq!(added["station"], STATION);
+    assert_eq!(added["mode"], serde_json::Value::Null);
+    assert!(added["label"].as_str().unwrap().starts_with("Runde um"));
+    assert_eq!(
+        added["scores"],
+        serde_json::json!([{
+            "id": id,
+            "score": GOOD_SCORE,
+            "color": GOOD_COLOR,
+            "station": STATION,
+        }])
+    );
+
+    let response = server
+        .post(&format!("/claim/{id}"))
+        .form(&leaderboard_claim(NORMAL_NICKNAME))
+        .await;
+    response.assert_status(StatusCode::SEE_OTHER);
+
+    let removed = next_event(&mut events, "removed").await;
+    assert_eq!(removed, serde_json::json!({"id": id}));
+}
+
+const EVENT: &str = "time-trial";
+
+async fn add_event(server: &TestServer, slug: &str, ordering: &str) {
+    let response = server
+        .post("/admin/events")
+        .authorization(ADMIN_TOKEN)
+        .json(&serde_json::json!({"slug": slug, "name": "Time Trial", "ordering": ordering}))
+        .await;
+
+    response.assert_status(StatusCode::CREATED);
+}
+
+/// Submits and claims a score for the event `slug` with a key of that event.
+async fn claim_event_score(server: &TestServer, slug: &str, key: &str, score: i32) {
+    let response = server
+        .post(&format!("/{slug}/backend/submit_score"))
+        .authorization(key)
+        .json(&GoodScoreFormat {
+            score,
+            color: GOOD_COLOR.to_string(),
+        })
+        .await;
+    response.assert_status_ok();
+    let id = response.json::<SubmitResponse>().id;
+
+    let response = server
+        .post(&format!("/{slug}/claim/{id}"))
+        .form(&leaderboard_claim(&format!("{slug} {score}")))
+        .await;
+    response.assert_status(StatusCode::SEE_OTHER);
+    response.assert_header(
+        "location",
+        format!("http://localhost:3000/{slug}/claim/list"),
+    );
 }
 
 #[tokio::test]
-async fn username_gets_filtered_for_html_chars() {
-    // when submitting a claim form with html chars in the username, these chars get html encoded
+async fn events_have_separate_leaderboards() {
     let server = setup_server().await;
+    add_event(&server, EVENT, "lower_is_better").await;
 
-    claim_score_username(&server, BAD_NICKNAME).await;
+    let response = server
+        .post(&format!("/{EVENT}/admin/keys"))
+        .authorization(ADMIN_TOKEN)
+        .json(&serde_json::json!({"name": STATION}))
+        .await;
+    response.assert_status(StatusCode::CREATED);
+    let event_key = response.json::<NewStationResponse>().key;
+
+    claim_event_score(&server, EVENT, &event_key, 20).await;
+    claim_event_score(&server, EVENT, &event_key, 10).await;
+    claim_score(&server).await;
+
+    let response = server.get(&format!("/{EVENT}/api/leaderboard")).await;
+    response.assert_status_ok();
+    response.assert_json(&serde_json::json!({
+        "scores": [
+            {"nickname": "time-trial 10", "score": 10, "placement": 1},
+            {"nickname": "time-trial 20", "score": 20, "placement": 2},
+        ],
+        "ordering": "lower_is_better",
+        "total": 2,
+        "limit": 50,
+        "offset": 0,
+    }));
+
+    let response = server.get(&format!("/{EVENT}/")).await;
+    response.assert_status_ok();
+    response.assert_text_contains("time-trial 10");
+    assert!(!response.text().contains(NORMAL_NICKNAME));
 
     let response = server.get("/").await;
+    response.assert_text_contains(NORMAL_NICKNAME);
+    assert!(!response.text().contains("time-trial 10"));
+
+    // keys only work for their own event
+    let response = server
+        .post(&format!("/{EVENT}/backend/submit_score"))
+        .authorization(&server.key)
+        .json(&GoodScoreFormat {
+            score: GOOD_SCORE,
+            color: GOOD_COLOR.to_string(),
+        })
+        .await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+
+    let response = server
+        .post("/backend/submit_score")
+        .authorization(&event_key)
+        .json(&GoodScoreFormat {
+            score: GOOD_SCORE,
+            color: GOOD_COLOR.to_string(),
+        })
+        .await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn events_have_separate_claim_lists() {
+    let server = setup_server().await;
+    add_event(&server, EVENT, "higher_is_better").await;
 
+    let id = submit_score(&server).await;
+
+    let response = server.get(&format!("/{EVENT}/claim/list")).await;
     response.assert_status_ok();
-    response.assert_text_contains(GOOD_SCORE.to_string());
-    let text = response.text();
+    assert!(!response.text().contains(&id.to_string()));
+
+    let response = server.get(&format!("/{EVENT}/claim/{id}")).await;
+    response.assert_status(StatusCode::NOT_FOUND);
+
+    let response = server.get("/claim/list").await;
+    response.assert_text_contains(id.to_string());
+}
+
+#[tokio::test]
+async fn claim_list_links_stay_in_their_event() {
+    let server = setup_server().await;
+    add_event(&server, EVENT, "higher_is_better").await;
+
+    let response = server
+        .post(&format!("/{EVENT}/admin/keys"))
+        .authorization(ADMIN_TOKEN)
+        .json(&serde_json::json!({"name": STATION}))
+        .await;
+    let event_key = response.json::<NewStationResponse>().key;
+
+    let response = server
+        .post(&format!("/{EVENT}/backend/submit_score"))
+        .authorization(&event_key)
+        .json(&GoodScoreFormat {
+            score: GOOD_SCORE,
+            color: GOOD_COLOR.to_string(),
+        })
+        .await;
+    let id = response.json::<SubmitResponse>().id;
+
+    let response = server.get(&format!("/{EVENT}/claim/list")).await;
+    response.assert_status_ok();
+
+    let re = regex::Regex::new(r#"<li data-id="[^"]+">\s*<a href="([^"]+)">"#).unwrap();
+    let text = response.text();
+    let href = &re.captures(&text).expect("a link to the score")[1];
+    assert_eq!(href, id.to_string(), "relative to the list of the event");
+
+    // resolved against `/{EVENT}/claim/list`
+    let response = server.get(&format!("/{EVENT}/claim/{href}")).await;
+    response.assert_status_ok();
+}
+
+#[tokio::test]
+async fn unknown_events_and_bad_slugs_get_rejected() {
+    let server = setup_server().await;
+
+    let response = server.get("/unknown/").await;
+    response.assert_status(StatusCode::NOT_FOUND);
+
+    for slug in ["claim", "Upper", "with space", ""] {
+        let response = server
+            .post("/admin/events")
+            .authorization(ADMIN_TOKEN)
+            .json(&serde_json::json!({"slug": slug, "name": "Nope"}))
+            .await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+    }
+
+    add_event(&server, EVENT, "higher_is_better").await;
+    let response = server
+        .post("/admin/events")
+        .authorization(ADMIN_TOKEN)
+        .json(&serde_json::json!({"slug": EVENT, "name": "Again"}))
+        .await;
+    response.assert_status(StatusCode::CONFLICT);
+
+    let response = server.get("/admin/events").await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn cant_submit_score_without_token() {
+    let server = setup_server().await;
+
+    let payload = GoodScoreFormat {
+        score: GOOD_SCORE,
+        color: GOOD_COLOR.to_string(),
+    };
+
+    let response = server.post("/backend/submit_score").json(&payload).await;
+
+    response.assert_status_not_ok();
+}
+
+#[tokio::test]
+async fn cant_submit_score_with_wrong_token() {
+    let server = setup_server().await;
+
+    let payload = GoodScoreFormat {
+        score: GOOD_SCORE,
+        color: GOOD_COLOR.to_string(),
+    };
+
+    let response = server
+        .post("/backend/submit_score")
+        .authorization(BAD_TOKEN)
+        .json(&payload)
+        .await;
+
+    response.assert_status_not_ok();
+}
+
+#[tokio::test]
+async fn cant_submit_malformed_score() {
+    let server = setup_server().await;
+
+    let response = server
+        .post("/backend/submit_score")
+        .authorization(&server.key)
+        .json(&BadScoreFormat {})
+        .await;
+
+    response.assert_status_not_ok();
+}
+
+#[tokio::test]
+async fn cant_claim_with_malformed_form() {
+    let server = setup_server().await;
+
+    let id = submit_score(&server).await;
+
+    let response = server
+        .post(&format!("/claim/{id}"))
+        .form(&BadFormSubmitFormat {})
+        .await;
+
+    response.assert_status_not_ok();
+}
+
+#[tokio::test]
+async fn stations_have_separate_keys() {
+    let server = setup_server().await;
+    let other_key = add_station(&server, "booth-2").await;
+
+    assert_ne!(server.key, other_key);
+
+    let payload = GoodScoreFormat {
+        score: GOOD_SCORE_2,
+        color: GOOD_COLOR_2.to_string(),
+    };
+
+    let response = server
+        .post("/backend/submit_score")
+        .authorization(&other_key)
+        .json(&payload)
+        .await;
+    response.assert_status_ok();
+
+    submit_score(&server).await;
+
+    let response = server.get("/claim/list").await;
+    response.assert_text_contains(format!("{GOOD_SCORE} ({STATION})"));
+    response.assert_text_contains(format!("{GOOD_SCORE_2} (booth-2)"));
+}
+
+#[tokio::test]
+async fn revoked_key_cant_submit_scores() {
+    let server = setup_server().await;
+
+    submit_score(&server).await;
+
+    let response = server
+        .delete(&format!("/admin/keys/{STATION}"))
+        .authorization(ADMIN_TOKEN)
+        .await;
+    response.assert_status(StatusCode::NO_CONTENT);
+
+    let payload = GoodScoreFormat {
+        score: GOOD_SCORE,
+        color: GOOD_COLOR.to_string(),
+    };
+
+    let response = server
+        .post("/backend/submit_score")
+        .authorization(&server.key)
+        .json(&payload)
+        .await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+
+    // the station can get a fresh key under the same name
+    let key = add_station(&server, STATION).await;
+    assert_ne!(key, server.key);
+}
+
+#[tokio::test]
+async fn station_names_are_unique() {
+    let server = setup_server().await;
+
+    let response = server
+        .post("/admin/keys")
+        .authorization(ADMIN_TOKEN)
+        .json(&serde_json::json!({"name": STATION}))
+        .await;
+
+    response.assert_status(StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn station_keys_need_admin_token() {
+    let server = setup_server().await;
+
+    let response = server
+        .post("/admin/keys")
+        .authorization(&server.key)
+        .json(&serde_json::json!({"name": "sneaky"}))
+        .await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+
+    let response = server.get("/admin/keys").await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+
+    let response = server.get("/admin/keys").authorization(ADMIN_TOKEN).await;
+    response.assert_status_ok();
+    response.assert_text_contains(STATION);
+    assert!(
+        !response.text().contains(&server.key),
+        "keys are never listed"
+    );
+}
+
+async fn submit_with_request_id(
+    server: &TestServer,
+    key: &str,
+    header: Option<&str>,
+    body: serde_json::Value,
+) -> axum_test::TestResponse {
+    let mut request = server
+        .post("/backend/submit_score")
+        .authorization(key)
+        .json(&body);
+
+    if let Some(header) = header {
+        request = request.add_header("idempotency-key", header);
+    }
+
+    request.await
+}
+
+#[tokio::test]
+async fn retried_submissions_dont_duplicate_scores() {
+    let server = setup_server().await;
+    let body = serde_json::json!({"score": GOOD_SCORE, "color": GOOD_COLOR});
+
+    let first = submit_with_request_id(&server, &server.key, Some("retry-1"), body.clone()).await;
+    first.assert_status_ok();
+    let retry = submit_with_request_id(&server, &server.key, Some("retry-1"), body).await;
+    retry.assert_status_ok();
+    assert_eq!(first.text(), retry.text());
+
+    // same thing with the request id in the body
+    let body = serde_json::json!({
+        "score": GOOD_SCORE_2, "color": GOOD_COLOR_2, "request_id": "retry-2"
+    });
+    let first = submit_with_request_id(&server, &server.key, None, body.clone()).await;
+    let retry = submit_with_request_id(&server, &server.key, None, body).await;
+    assert_eq!(first.text(), retry.text());
+
+    let list = server.get("/claim/list").await.text();
+    assert_eq!(
+        list.matches(&format!("{GOOD_SCORE} ({STATION})")).count(),
+        1
+    );
+    assert_eq!(
+        list.matches(&format!("{GOOD_SCORE_2} ({STATION})")).count(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn request_ids_are_per_station() {
+    let server = setup_server().await;
+    let other_key = add_station(&server, "booth-2").await;
+    let body = serde_json::json!({"score": GOOD_SCORE, "color": GOOD_COLOR});
+
+    let first = submit_with_request_id(&server, &server.key, Some("1"), body.clone()).await;
+    let other = submit_with_request_id(&server, &other_key, Some("1"), body).await;
+    other.assert_status_ok();
+
+    assert_ne!(
+        first.json::<SubmitResponse>().id,
+        other.json::<SubmitResponse>().id
+    );
+}
+
+#[tokio::test]
+async fn conflicting_request_ids_get_rejected() {
+    let server = setup_server().await;
+    let body = serde_json::json!({"score": GOOD_SCORE, "color": GOOD_COLOR, "request_id": "a"});
+
+    let response = submit_with_request_id(&server, &server.key, Some("b"), body).await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+
+    let body = serde_json::json!({"score": GOOD_SCORE, "color": GOOD_COLOR, "request_id": ""});
+    let response = submit_with_request_id(&server, &server.key, None, body).await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[derive(Deserialize)]
+struct MatchResponse {
+    match_id: Uuid,
+    ids: Vec<Uuid>,
+    codes: Vec<String>,
+}
+
+async fn submit_match(server: &TestBooth, body: serde_json::Value) -> axum_test::TestResponse {
+    server
+        .post("/backend/submit_match")
+        .authorization(&server.key)
+        .json(&body)
+        .await
+}
+
+#[tokio::test]
+async fn matches_get_submitted_at_once() {
+    let server = setup_server().await;
+
+    let response = submit_match(
+        &server,
+        serde_json::json!({"scores": [
+            {"score": GOOD_SCORE, "color": GOOD_COLOR},
+            {"score": GOOD_SCORE_2, "color": GOOD_COLOR_2},
+        ]}),
+    )
+    .await;
+    response.assert_status_ok();
+    let response: MatchResponse = response.json();
+    assert_eq!(response.ids.len(), 2);
+    assert_eq!(response.codes.len(), 2);
+    assert_ne!(response.codes[0], response.codes[1]);
+    assert!(!response.match_id.is_nil());
+
+    // the ids are in the order of the submitted scores
+    let list = server.get("/claim/list").await.text();
+    let entry = |id: Uuid| {
+        let start = list.find(&format!("data-id=\"{id}\"")).unwrap();
+        list[start..].split("</li>").next().unwrap().to_string()
+    };
+    assert!(entry(response.ids[0]).contains(&format!("{GOOD_COLOR}: {GOOD_SCORE}")));
+    assert!(entry(response.ids[1]).contains(&format!("{GOOD_COLOR_2}: {GOOD_SCORE_2}")));
+}
+
+#[tokio::test]
+async fn claim_list_groups_scores_by_match() {
+    let server = setup_server().await;
+
+    let first = submit_match(
+        &server,
+        serde_json::json!({"scores": [
+            {"score": GOOD_SCORE, "color": GOOD_COLOR},
+            {"score": GOOD_SCORE_2, "color": GOOD_COLOR_2},
+        ]}),
+    )
+    .await
+    .json::<MatchResponse>();
+
+    // same colors in the next round
+    let second = submit_match(
+        &server,
+        serde_json::json!({
+            "scores": [{"score": 42, "color": GOOD_COLOR}],
+            "mode": "Time Trial",
+            "started_at": now() - 120,
+            "ended_at": now(),
+        }),
+    )
+    .await
+    .json::<MatchResponse>();
+
+    let list = server.get("/claim/list").await.text();
+    let section = |id: Uuid| {
+        let start = list.find(&format!("data-match=\"{id}\"")).unwrap();
+        list[start..]
+            .split("</section>")
+            .next()
+            .unwrap()
+            .to_string()
+    };
+
+    let first_section = section(first.match_id);
+    assert!(first_section.contains(&first.ids[0].to_string()));
+    assert!(first_section.contains(&first.ids[1].to_string()));
+    assert!(!first_section.contains(&second.ids[0].to_string()));
+
+    let second_section = section(second.match_id);
+    assert!(second_section.contains("Time Trial"));
+    assert!(second_section.contains(&second.ids[0].to_string()));
+
+    // newest match first
+    assert!(
+        list.find(&second.match_id.to_string()).unwrap()
+            < list.find(&first.match_id.to_string()).unwrap()
+    );
+
+    let response = submit_match(
+        &server,
+        serde_json::json!({
+            "scores": [{"score": 42, "color": GOOD_COLOR}],
+            "started_at": now(),
+            "ended_at": now() - 120,
+        }),
+    )
+    .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn invalid_matches_insert_nothing() {
+    let server = setup_server().await;
+
+    let response = submit_match(
+        &server,
+        serde_json::json!({"scores": [
+            {"score": GOOD_SCORE, "color": GOOD_COLOR},
+            {"score": -1, "color": GOOD_COLOR_2},
+        ]}),
+    )
+    .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+
+    let response = submit_match(
+        &server,
+        serde_json::json!({"scores": [
+            {"score": GOOD_SCORE, "color": GOOD_COLOR},
+            {"score": GOOD_SCORE_2, "color": GOOD_COLOR},
+        ]}),
+    )
+    .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+
+    let response = submit_match(&server, serde_json::json!({"scores": []})).await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+
+    let list = server.get("/claim/list").await.text();
+    assert!(!list.contains(&GOOD_SCORE.to_string()));
+}
+
+#[tokio::test]
+async fn retried_matches_dont_duplicate_scores() {
+    let server = setup_server().await;
+    let body = serde_json::json!({
+        "scores": [{"score": GOOD_SCORE, "color": GOOD_COLOR}],
+        "request_id": "round-1",
+    });
+
+    let first = submit_match(&server, body.clone()).await;
+    let retry = submit_match(&server, body).await;
+    retry.assert_status_ok();
+    assert_eq!(first.text(), retry.text());
+
+    let list = server.get("/claim/list").await.text();
+    assert_eq!(
+        list.matches(&format!("{GOOD_SCORE} ({STATION})")).count(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn reused_request_ids_get_rejected() {
+    let server = setup_server().await;
+    let body = serde_json::json!({"score": GOOD_SCORE, "color": GOOD_COLOR});
+    submit_with_request_id(&server, &server.key, Some("reused"), body)
+        .await
+        .assert_status_ok();
+
+    // a different score under the same id
+    let body = serde_json::json!({"score": GOOD_SCORE_2, "color": GOOD_COLOR});
+    let response = submit_with_request_id(&server, &server.key, Some("reused"), body).await;
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+
+    // the same id for a match
+    let response = submit_match(
+        &server,
+        serde_json::json!({
+            "scores": [{"score": GOOD_SCORE, "color": GOOD_COLOR}],
+            "request_id": "reused",
+        }),
+    )
+    .await;
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+
+    let list = server.get("/claim/list").await.text();
+    assert!(!list.contains(&GOOD_SCORE_2.to_string()));
+    assert_eq!(
+        list.matches(&format!("{GOOD_SCORE} ({STATION})")).count(),
+        1
+    );
+}
+
+fn signed_submission(body: &str, timestamp: i64, nonce: &str) -> [(&'static str, String); 3] {
+    let timestamp = timestamp.to_string();
+
+    let mut mac = Hmac::<sha2::Sha256>::new_from_slice(SIGNING_SECRET.as_bytes()).unwrap();
+    mac.update(format!("{timestamp}\n{nonce}\n{body}").as_bytes());
+    let signature = hex::encode(mac.finalize().into_bytes());
+
+    [
+        ("x-ropeat-timestamp", timestamp),
+        ("x-ropeat-nonce", nonce.to_string()),
+        ("x-ropeat-signature", signature),
+    ]
+}
+
+fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+async fn setup_signing_server() -> TestBooth {
+    setup_server_with_config(LeaderboardConfig {
+        signing: Some(SigningConfig::new(SIGNING_SECRET)),
+        ..test_config(DatabaseConfig::in_memory())
+    })
+    .await
+}
+
+async fn post_signed(
+    server: &TestBooth,
+    body: &str,
+    headers: [(&'static str, String); 3],
+) -> axum_test::TestResponse {
+    let mut request = server
+        .post("/backend/submit_score")
+        .authorization(&server.key)
+        .content_type("application/json")
+        .text(body);
+
+    for (name, value) in headers {
+        request = request.add_header(name, value);
+    }
+
+    request.await
+}
+
+const SIGNED_BODY: &str = r##"{"score": 1337, "color": "#123456"}"##;
+
+#[tokio::test]
+async fn signed_submission_gets_accepted() {
+    let server = setup_signing_server().await;
+
+    let headers = signed_submission(SIGNED_BODY, now(), "nonce-1");
+    let response = post_signed(&server, SIGNED_BODY, headers).await;
+    response.assert_status_ok();
+
+    let response = server.get("/claim/list").await;
+    response.assert_text_contains(GOOD_SCORE.to_string());
+}
+
+#[tokio::test]
+async fn unsigned_submission_gets_rejected_when_signing_is_enabled() {
+    let server = setup_signing_server().await;
+
+    let payload = GoodScoreFormat {
+        score: GOOD_SCORE,
+        color: GOOD_COLOR.to_string(),
+    };
+
+    let response = server
+        .post("/backend/submit_score")
+        .authorization(&server.key)
+        .json(&payload)
+        .await;
+
+    response.assert_status(StatusCode::UNAUTHORIZED);
+    response.assert_text_contains("Missing signature");
+}
+
+#[tokio::test]
+async fn tampered_submission_gets_rejected() {
+    let server = setup_signing_server().await;
+
+    let headers = signed_submission(SIGNED_BODY, now(), "nonce-1");
+    let tampered = SIGNED_BODY.replace("1337", "9999");
+    let response = post_signed(&server, &tampered, headers).await;
+
+    response.assert_status(StatusCode::UNAUTHORIZED);
+    response.assert_text_contains("Bad signature");
+}
+
+#[tokio::test]
+async fn stale_submission_gets_rejected() {
+    let server = setup_signing_server().await;
+
+    let headers = signed_submission(SIGNED_BODY, now() - 3600, "nonce-1");
+    let response = post_signed(&server, SIGNED_BODY, headers).await;
+
+    response.assert_status(StatusCode::UNAUTHORIZED);
+    response.assert_text_contains("Stale timestamp");
+}
+
+#[tokio::test]
+async fn replayed_submission_gets_rejected() {
+    let server = setup_signing_server().await;
+
+    let headers = signed_submission(SIGNED_BODY, now(), "nonce-1");
+    let response = post_signed(&server, SIGNED_BODY, headers.clone()).await;
+    response.assert_status_ok();
+
+    let response = post_signed(&server, SIGNED_BODY, headers).await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+    response.assert_text_contains("Reused nonce");
+}
+
+#[tokio::test]
+async fn errors_on_browser_routes_are_html_pages() {
+    let server = setup_server().await;
+
+    let response = server.get(&format!("/claim/{}", Uuid::new_v4())).await;
+
+    response.assert_status(StatusCode::NOT_FOUND);
+    assert!(response
+        .header("content-type")
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+    response.assert_text_contains("Fehler 404");
+}
+
+#[tokio::test]
+async fn errors_on_backend_routes_are_json() {
+    let server = setup_server().await;
+
+    let payload = GoodScoreFormat {
+        score: GOOD_SCORE,
+        color: "#12345".to_string(),
+    };
+
+    let response = server
+        .post("/backend/submit_score")
+        .authorization(&server.key)
+        .json(&payload)
+        .await;
+
+    response.assert_status(StatusCode::BAD_REQUEST);
+    response.assert_json(&serde_json::json!({"error": "Malformed color"}));
+
+    let response = server.post("/backend/submit_score").json(&payload).await;
+
+    response.assert_status(StatusCode::UNAUTHORIZED);
+    assert!(response.json::<serde_json::Value>()["error"].is_string());
+}
+
+#[tokio::test]
+async fn bad_submitted_score_doesnt_show_on_unclaimed_list() {
+    // when uploading a score from the game with an invalid token, this score is not present in the unclaimed scores list
+    let server = setup_server().await;
+
+    let payload = GoodScoreFormat {
+        score: GOOD_SCORE,
+        color: GOOD_COLOR.to_string(),
+    };
+
+    let response = server
+        .post("/backend/submit_score")
+        .authorization(BAD_TOKEN)
+        .json(&payload)
+        .await;
+
+    response.assert_status_not_ok();
+
+    let response = server.get("/claim/list").await;
+
+    response.assert_status_ok();
+    assert!(
+        !response.text().contains(&GOOD_SCORE.to_string()),
+        "The score shows up!"
+    );
+}
+
+fn leaderboard_claim(nickname: &str) -> GoodFormSubmitFormat {
+    GoodFormSubmitFormat {
+        wants_leaderboard: Some(true),
+        wants_raffle: None,
+
+        nickname: nickname.to_string(),
+        email: "".to_string(),
+        firstname: "".to_string(),
+        lastname: "".to_owned(),
+        newsletter: false,
+        data_protection: None,
+        occupation: "".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn double_claim_score_doesnt_work() {
+    // when submitting a claim form twice for the same score, the second one doesn't work
+    let server = setup_server().await;
+
+    let id = submit_score(&server).await;
+
+    let response = server
+        .post(&format!("/claim/{id}"))
+        .form(&leaderboard_claim(NORMAL_NICKNAME))
+        .await;
+    response.assert_status(StatusCode::SEE_OTHER);
+
+    let response = server
+        .post(&format!("/claim/{id}"))
+        .form(&leaderboard_claim(NORMAL_NICKNAME_2))
+        .await;
+    response.assert_status(StatusCode::CONFLICT);
+    response.assert_text_contains("bereits eingelöst");
+
+    let response = server.get(&format!("/claim/{id}")).await;
+    response.assert_status(StatusCode::CONFLICT);
+
+    let response = server.get("/").await;
+    let text = response.text();
+    assert_eq!(text.matches(NORMAL_NICKNAME).count(), 1);
+    assert!(!text.contains(NORMAL_NICKNAME_2));
+}
+
+#[tokio::test]
+async fn missing_nickname_rerenders_form() {
+    let server = setup_server().await;
+
+    let id = submit_score(&server).await;
+
+    let response = server
+        .post(&format!("/claim/{id}"))
+        .form(&leaderboard_claim("  "))
+        .await;
+
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    response.assert_text_contains("Bitte gib ein Pseudonym für das Scoreboard an.");
+    response.assert_text_contains(
+        r#"name="wants_leaderboard" type="checkbox" class="form-check-input" value="true" checked"#,
+    );
+
+    // the score can still be claimed afterwards
+    let response = server
+        .post(&format!("/claim/{id}"))
+        .form(&leaderboard_claim(NORMAL_NICKNAME))
+        .await;
+    response.assert_status(StatusCode::SEE_OTHER);
+}
+
+#[tokio::test]
+async fn incomplete_raffle_data_keeps_entered_values() {
+    let server = setup_server().await;
+
+    let id = submit_score(&server).await;
+
+    let body = GoodFormSubmitFormat {
+        wants_leaderboard: Some(true),
+        wants_raffle: Some(true),
+
+        nickname: BAD_NICKNAME.to_string(),
+        email: "testy@example.com".to_string(),
+        firstname: "Testy".to_string(),
+        lastname: "".to_string(),
+        newsletter: true,
+        data_protection: None,
+        occupation: "university".to_string(),
+    };
+
+    let response = server.post(&format!("/claim/{id}")).form(&body).await;
+
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    response.assert_text_contains("Bitte gib deinen Nachnamen an.");
+    response.assert_text_contains("Ohne Zustimmung zum Datenschutz");
+    response.assert_text_contains(r#"value="testy@example.com""#);
+    response.assert_text_contains(r#"value="Testy""#);
+    response.assert_text_contains(r#"value="university" selected"#);
+    response.assert_text_contains(r#"value="true" checked"#);
+    assert!(
+        !response.text().contains(BAD_NICKNAME),
+        "entered values have to be escaped"
+    );
+
+    // nothing was claimed
+    let response = server.get("/").await;
+    assert!(!response.text().contains(&GOOD_SCORE.to_string()));
+}
+
+fn raffle_claim() -> GoodFormSubmitFormat {
+    GoodFormSubmitFormat {
+        wants_leaderboard: None,
+        wants_raffle: Some(true),
+
+        nickname: "".to_string(),
+        email: "testy@example.com".to_string(),
+        firstname: "Testy".to_string(),
+        lastname: "McTestface".to_string(),
+        newsletter: false,
+        data_protection: Some(true),
+        occupation: "university".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn raffle_entries_go_to_the_configured_csv_file() {
+    let path = std::env::temp_dir().join(format!("ropeat-raffle-{}.csv", Uuid::new_v4()));
+    let server = setup_server_with_config(LeaderboardConfig {
+        raffle: RaffleConfig::Csv { path: path.clone() },
+        ..test_config(DatabaseConfig::in_memory())
+    })
+    .await;
+
+    let id = submit_score(&server).await;
+    let response = server
+        .post(&format!("/claim/{id}"))
+        .form(&raffle_claim())
+        .await;
+    response.assert_status(StatusCode::SEE_OTHER);
+
+    // the outbox worker writes the entry right after the claim
+    let mut content = String::new();
+    for _ in 0..50 {
+        content = std::fs::read_to_string(&path).unwrap_or_default();
+        if !content.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    std::fs::remove_file(&path).unwrap();
+
+    let lines: Vec<_> = content.lines().collect();
+    assert_eq!(lines.len(), 2, "header and one entry");
+    assert!(lines[1].starts_with(&format!(
+        "default,{id},{GOOD_SCORE},Testy,McTestface,testy@example.com,university,false,"
+    )));
+}
+
+#[tokio::test]
+async fn raffle_entries_go_to_the_configured_webhook() {
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let webhook = axum::Router::new().route(
+        "/raffle",
+        axum::routing::post(move |body: String| async move {
+            sender.send(body).unwrap();
+        }),
+    );
+    let webhook = TestServer::builder()
+        .http_transport()
+        .build(webhook)
+        .unwrap();
+    let url = webhook.server_address().unwrap().join("raffle").unwrap();
+
+    let server = setup_server_with_config(LeaderboardConfig {
+        raffle: RaffleConfig::Webhook {
+            url: url.to_string(),
+        },
+        ..test_config(DatabaseConfig::in_memory())
+    })
+    .await;
+
+    let id = submit_score(&server).await;
+    let response = server
+        .post(&format!("/claim/{id}"))
+        .form(&raffle_claim())
+        .await;
+    response.assert_status(StatusCode::SEE_OTHER);
+
+    let entry: serde_json::Value = serde_json::from_str(&receiver.recv().await.unwrap()).unwrap();
+    assert_eq!(entry["score_id"], id.to_string());
+    assert_eq!(entry["email"], "testy@example.com");
+    assert_eq!(entry["occupation"], "university");
+    assert_eq!(entry["newsletter"], false);
+}
+
+/// The raffle entries the outbox worker gave up on, once there are any.
+async fn wait_for_failed_raffle_entries(server: &TestServer) -> Vec<serde_json::Value> {
+    let mut failed = Vec::new();
+    for _ in 0..50 {
+        failed = server
+            .get("/admin/raffle/failed")
+            .authorization(ADMIN_TOKEN)
+            .await
+            .json::<Vec<serde_json::Value>>();
+        if !failed.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    failed
+}
+
+#[tokio::test]
+async fn hpi_error_pages_dont_count_as_submitted() {
+    let form = axum::Router::new().route(
+        "/gewinnspiel/",
+        axum::routing::get(|| async {
+            axum::response::Html(
+                r#"<form method="post"><input type="hidden" name="zz_id" value="a1b2c3d4"></form>"#,
+            )
+        })
+        .post(|| async { StatusCode::INTERNAL_SERVER_ERROR }),
+    );
+    let form = TestServer::builder().http_transport().build(form).unwrap();
+    let url = form.server_address().unwrap().join("gewinnspiel/").unwrap();
+
+    let server = setup_server_with_config(LeaderboardConfig {
+        raffle: RaffleConfig::Hpi(Box::new(HpiFormConfig {
+            url: url.to_string(),
+            ..Default::default()
+        })),
+        raffle_outbox: OutboxConfig {
+            max_attempts: 1,
+            ..Default::default()
+        },
+        ..test_config(DatabaseConfig::in_memory())
+    })
+    .await;
+
+    let id = submit_score(&server).await;
+    let response = server
+        .post(&format!("/claim/{id}"))
+        .form(&raffle_claim())
+        .await;
+    response.assert_status(StatusCode::SEE_OTHER);
+
+    let failed = wait_for_failed_raffle_entries(&server).await;
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0]["entry"]["score_id"], id.to_string());
+    assert!(failed[0]["last_error"].as_str().unwrap().contains("500"));
+}
+
+#[tokio::test]
+async fn hanging_raffle_sinks_time_out() {
+    let webhook = axum::Router::new().route(
+        "/raffle",
+        axum::routing::post(|| async {
+            tokio::time::sleep(Duration::from_secs(30)).await;
+        }),
+    );
+    let webhook = TestServer::builder()
+        .http_transport()
+        .build(webhook)
+        .unwrap();
+    let url = webhook.server_address().unwrap().join("raffle").unwrap();
+
+    let server = setup_server_with_config(LeaderboardConfig {
+        raffle: RaffleConfig::Webhook {
+            url: url.to_string(),
+        },
+        raffle_outbox: OutboxConfig {
+            max_attempts: 1,
+            timeout: 1,
+            ..Default::default()
+        },
+        ..test_config(DatabaseConfig::in_memory())
+    })
+    .await;
+
+    let id = submit_score(&server).await;
+    let response = server
+        .post(&format!("/claim/{id}"))
+        .form(&raffle_claim())
+        .await;
+    response.assert_status(StatusCode::SEE_OTHER);
+
+    let failed = wait_for_failed_raffle_entries(&server).await;
+    assert_eq!(failed.len(), 1, "the request gave up after a second");
+}
+
+#[tokio::test]
+async fn failed_raffle_entries_can_be_retried() {
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let down = Arc::new(AtomicBool::new(true));
+    let webhook = axum::Router::new().route(
+        "/raffle",
+        axum::routing::post({
+            let down = down.clone();
+            move |body: String| async move {
+                if down.load(Ordering::SeqCst) {
+                    return StatusCode::BAD_GATEWAY;
+                }
+                sender.send(body).unwrap();
+                StatusCode::OK
+            }
+        }),
+    );
+    let webhook = TestServer::builder()
+        .http_transport()
+        .build(webhook)
+        .unwrap();
+    let url = webhook.server_address().unwrap().join("raffle").unwrap();
+
+    let server = setup_server_with_config(LeaderboardConfig {
+        raffle: RaffleConfig::Webhook {
+            url: url.to_string(),
+        },
+        raffle_outbox: OutboxConfig {
+            max_attempts: 1,
+            ..Default::default()
+        },
+        ..test_config(DatabaseConfig::in_memory())
+    })
+    .await;
+
+    // the claim goes through even though the webhook is down
+    let id = submit_score(&server).await;
+    let response = server
+        .post(&format!("/claim/{id}"))
+        .form(&raffle_claim())
+        .await;
+    response.assert_status(StatusCode::SEE_OTHER);
+
+    let failed = wait_for_failed_raffle_entries(&server).await;
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0]["entry"]["score_id"], id.to_string());
+    assert!(failed[0]["last_error"].as_str().unwrap().contains("502"));
+
+    server
+        .get("/admin/raffle/failed")
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    down.store(false, Ordering::SeqCst);
+    let entry_id = failed[0]["id"].as_i64().unwrap();
+    server
+        .post(&format!("/admin/raffle/failed/{entry_id}/retry"))
+        .authorization(ADMIN_TOKEN)
+        .await
+        .assert_status(StatusCode::ACCEPTED);
+
+    let entry = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    let entry: serde_json::Value = serde_json::from_str(&entry).unwrap();
+    assert_eq!(entry["email"], "testy@example.com");
+
+    let failed = server
+        .get("/admin/raffle/failed")
+        .authorization(ADMIN_TOKEN)
+        .await
+        .json::<Vec<serde_json::Value>>();
+    assert!(failed.is_empty());
+
+    server
+        .post(&format!("/admin/raffle/failed/{entry_id}/retry"))
+        .authorization(ADMIN_TOKEN)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn concurrent_claims_only_succeed_once() {
+    let database = temporary_database();
+    let server = setup_server_with_database(database.clone()).await;
+
+    let id = submit_score(&server).await;
+
+    let first_claim = leaderboard_claim(NORMAL_NICKNAME);
+    let second_claim = leaderboard_claim(NORMAL_NICKNAME_2);
+    let (first, second) = tokio::join!(
+        server.post(&format!("/claim/{id}")).form(&first_claim),
+        server.post(&format!("/claim/{id}")).form(&second_claim),
+    );
+
+    let mut statuses = [first.status_code(), second.status_code()];
+    statuses.sort();
+    assert_eq!(statuses, [StatusCode::SEE_OTHER, StatusCode::CONFLICT]);
+
+    let response = server.get("/").await;
+    let text = response.text();
+    assert_eq!(
+        text.matches(NORMAL_NICKNAME).count() + text.matches(NORMAL_NICKNAME_2).count(),
+        1
+    );
+
+    let _ = std::fs::remove_file(database.path.unwrap());
+}
+
+#[tokio::test]
+async fn username_gets_filtered_for_html_chars() {
+    // when submitting a claim form with html chars in the username, these chars get html encoded
+    let server = setup_server().await;
+
+    claim_score_username(&server, BAD_NICKNAME).await;
+
+    let response = server.get("/").await;
+
+    response.assert_status_ok();
+    response.assert_text_contains(GOOD_SCORE.to_string());
+    let text = response.text();
 
     assert!(!text.contains(BAD_NICKNAME));
     response.assert_text_contains(SANITIZED_NICKNAME);
 }
 
+#[tokio::test]
+async fn api_returns_nicknames_as_entered() {
+    let server = setup_server().await;
+    let nickname = "Tom & Jerry <3";
+
+    claim_score_username(&server, nickname).await;
+
+    let response = server.get("/api/leaderboard").await;
+    response.assert_status_ok();
+    let page: serde_json::Value = response.json();
+    assert_eq!(page["scores"][0]["nickname"], nickname);
+
+    let response = server.get("/").await;
+    response.assert_text_contains("Tom &#38; Jerry &#60;3");
+}
+
 #[tokio::test]
 #[ignore = "not implemented"]
 async fn form_submit_unset_checkboxes_dont_copy_internally() {
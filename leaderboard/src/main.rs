@@ -1,15 +1,91 @@
-use backend::{routes, LeaderboardError};
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+
+use backend::{routes, LeaderboardConfig, LeaderboardError};
+use clap::Parser;
+
+const DEFAULT_CONFIG: &str = "leaderboard.toml";
+
+/// Every option can be given as a flag, as an environment variable or in the
+/// config file. Flags win over environment variables, which win over the file.
+#[derive(Parser, Debug)]
+#[command(version, about = "Leaderboard for the Ropeat game")]
+struct Cli {
+    /// TOML file with the configuration [default: leaderboard.toml, if it exists]
+    #[arg(long, env = "ROPEAT_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// token for the `/admin` routes, e.g. to hand out station keys
+    #[arg(long, env = "ROPEAT_ADMIN_TOKEN", hide_env_values = true)]
+    admin_token: Option<String>,
+
+    /// address the server listens on
+    #[arg(long, env = "ROPEAT_BIND")]
+    bind: Option<SocketAddr>,
+
+    /// public url of the server, used for redirects
+    #[arg(long, env = "ROPEAT_BASE_URL")]
+    base_url: Option<String>,
+
+    /// seconds a submitted score can be claimed before it expires
+    #[arg(long, env = "ROPEAT_CLAIM_TTL")]
+    claim_ttl: Option<u64>,
+
+    /// path of the SQLite database
+    #[arg(long, env = "ROPEAT_DATABASE")]
+    database: Option<PathBuf>,
+}
+
+impl Cli {
+    fn into_config(self) -> Result<LeaderboardConfig, LeaderboardError> {
+        // the default config file is optional, an explicitly named one is not
+        let mut config = match self.config {
+            Some(path) => LeaderboardConfig::from_file(path)?,
+            None if Path::new(DEFAULT_CONFIG).exists() => {
+                LeaderboardConfig::from_file(DEFAULT_CONFIG)?
+            }
+            None => LeaderboardConfig::default(),
+        };
+
+        if let Some(admin_token) = self.admin_token {
+            config.admin_token = admin_token;
+        }
+
+        if let Some(bind) = self.bind {
+            config.bind = bind;
+        }
+
+        if let Some(base_url) = self.base_url {
+            config.base_url = base_url;
+        }
+
+        if let Some(claim_ttl) = self.claim_ttl {
+            config.claim_ttl = claim_ttl;
+        }
+
+        if let Some(database) = self.database {
+            config.database.path = Some(database);
+        }
+
+        Ok(config)
+    }
+}
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<(), LeaderboardError> {
-    let auth_token = "abcd";
-    let app: axum::Router = routes(auth_token).await?;
+    let config = Cli::parse().into_config()?;
+    let bind = config.bind;
+    let base_url = config.base_url().to_string();
+
+    let app: axum::Router = routes(config).await?;
 
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:3000")
+    let listener = tokio::net::TcpListener::bind(bind)
         .await
         .map_err(|x| -> LeaderboardError { LeaderboardError::TcpListener(x) })?;
 
-    println!("Running on http://localhost:3000/");
+    println!("Running on {base_url}/");
 
     axum::serve(listener, app)
         .await